@@ -1,11 +1,18 @@
 use enum_dispatch::enum_dispatch;
 use serde::{Serialize, de::DeserializeOwned};
 use std::fmt::Debug;
+use std::path::Path;
 use strum::{Display, EnumString};
 
 use super::{
-    cards_client::CardsClient, client::ClientBehavior, decks_client::DecksClient, error::APIError,
-    models_client::ModelsClient, notes_client::NotesClient,
+    cards_client::CardsClient,
+    client::{ClientBehavior, RecordingClient, ReplayClient},
+    decks_client::DecksClient,
+    error::APIError,
+    gui_client::GuiClient,
+    media_client::MediaClient,
+    models_client::ModelsClient,
+    notes_client::NotesClient,
 };
 
 #[cfg(feature = "reqwest_blocking")]
@@ -14,24 +21,34 @@ use super::client::ReqwestClient;
 #[cfg(feature = "ureq_blocking")]
 use super::client::UreqClient;
 
+/// Timeout of a `multi` request in seconds.
+const MULTI_TIMEOUT: u8 = 120;
+
 #[derive(Debug, Clone, Display, EnumString)]
 #[enum_dispatch(ClientBehavior)]
+#[allow(clippy::enum_variant_names)]
 pub enum AnkiConnectClient {
     #[cfg(feature = "reqwest_blocking")]
     ReqwestClient(ReqwestClient),
     #[cfg(feature = "ureq_blocking")]
     UreqClient(UreqClient),
+    #[strum(disabled)]
+    RecordingClient(RecordingClient),
+    #[strum(disabled)]
+    ReplayClient(ReplayClient),
 }
 
 impl AnkiConnectClient {
-    pub fn new(url: Option<&str>, port: Option<u32>) -> Self {
+    /// Creates a client for the AnkiConnect instance at `url:port`, by default
+    /// `http://localhost:8765`. The `key` is needed when `apiKey` is set in the AnkiConnect config.
+    pub fn new(url: Option<&str>, port: Option<u32>, key: Option<&str>) -> Self {
         #[cfg(feature = "reqwest_blocking")]
         {
-            Self::ReqwestClient(ReqwestClient::new(url, port))
+            Self::ReqwestClient(ReqwestClient::new(url, port, key))
         }
         #[cfg(feature = "ureq_blocking")]
         {
-            Self::UreqClient(UreqClient::new(url, port))
+            Self::UreqClient(UreqClient::new(url, port, key))
         }
 
         // fallback if neither feature is enabled
@@ -39,7 +56,16 @@ impl AnkiConnectClient {
         // compile_error!("Enable at least one of `ureq_blocking` or `reqwest_blocking` features.");
     }
 
-    #[allow(unused)]
+    /// Wraps the client to write every request and its response to a JSONL trace at `path`.
+    pub fn record(self, path: &Path) -> std::io::Result<Self> {
+        Ok(Self::RecordingClient(RecordingClient::new(self, path)?))
+    }
+
+    /// Creates a client answering requests from a trace written by [`AnkiConnectClient::record`].
+    pub fn replay(path: &Path, loose: bool) -> std::io::Result<Self> {
+        Ok(Self::ReplayClient(ReplayClient::open(path, loose)?))
+    }
+
     pub fn cards(&self) -> CardsClient<'_> {
         CardsClient(self)
     }
@@ -48,6 +74,14 @@ impl AnkiConnectClient {
         DecksClient(self)
     }
 
+    pub fn gui(&self) -> GuiClient<'_> {
+        GuiClient(self)
+    }
+
+    pub fn media(&self) -> MediaClient<'_> {
+        MediaClient(self)
+    }
+
     pub fn models(&self) -> ModelsClient<'_> {
         ModelsClient(self)
     }
@@ -68,19 +102,54 @@ impl AnkiConnectClient {
             ) {
             Ok(_) => Ok(true),
             Err(err) => match err {
-                APIError::UreqError(_) => Ok(false),
+                APIError::Http { .. } => Ok(false),
                 _ => Err(APIError::FailedConnection(err.to_string())),
             },
         }
     }
 
+    /// Gets the names of the Anki profiles.
+    pub fn get_profiles(&self) -> Result<Vec<String>, APIError> {
+        self.request("getProfiles", None::<()>)
+            .map(|response| response.result.unwrap_or_default())
+    }
+
+    /// Gets the name of the profile that is currently open in Anki.
+    pub fn get_active_profile(&self) -> Result<String, APIError> {
+        self.request("getActiveProfile", None::<()>)
+            .map(|response| response.result.unwrap_or_default())
+    }
+
+    /// Closes the current profile and opens the given one, nothing is done if it is already open.
+    pub fn load_profile(&self, name: &str) -> Result<bool, APIError> {
+        self.request("loadProfile", Some(params::LoadProfile::new(name)))
+            .map(|response| response.result.unwrap_or_default())
+    }
+
+    /// Syncs the collection of the current profile with AnkiWeb, like the Sync button of Anki.
+    pub fn sync(&self) -> Result<(), APIError> {
+        // Syncing a large collection takes longer than the default timeout
+        self.request_with_timeout::<Option<()>, _>("sync", None::<()>, Some(120))
+            .map(|_| ())
+    }
+
     pub fn multi<P, R>(&self, actions: Vec<&params::Action<P>>) -> Result<Vec<R>, APIError>
     where
         P: Serialize + std::fmt::Debug,
         R: DeserializeOwned + std::fmt::Debug,
     {
-        self.request::<Vec<R>, _>("multi", Some(params::Multi::new(actions)))
-            .map(|response| response.result.unwrap())
+        let key = self.key();
+        let actions = actions
+            .into_iter()
+            .map(|action| params::MultiAction::new(action, key))
+            .collect();
+        // A batch of hundreds of notes takes longer than the default timeout
+        self.request_with_timeout::<Vec<R>, _>(
+            "multi",
+            Some(params::Multi::new(actions)),
+            Some(MULTI_TIMEOUT),
+        )
+        .map(|response| response.result.unwrap())
     }
 }
 
@@ -93,7 +162,7 @@ pub mod response {
     #[derive(Debug, Serialize, Deserialize, new)]
     #[serde(rename_all = "camelCase")]
     pub struct BasicResponse {
-        pub result: Option<String>,
+        pub result: Option<serde_json::Value>,
         pub error: Option<String>,
     }
 }
@@ -109,10 +178,26 @@ pub mod params {
         actions: Vec<String>,
     }
 
+    #[derive(Debug, Serialize, new)]
+    #[serde(rename_all = "camelCase")]
+    pub struct LoadProfile<'a> {
+        name: &'a str,
+    }
+
     #[derive(Debug, Serialize, new)]
     #[serde(rename_all = "camelCase")]
     pub struct Multi<'a, P: Serialize + std::fmt::Debug> {
-        actions: Vec<&'a Action<'a, P>>,
+        actions: Vec<MultiAction<'a, P>>,
+    }
+
+    /// Action of a `multi` request, AnkiConnect checks the API key of each action separately.
+    #[derive(Debug, Serialize, new)]
+    #[serde(rename_all = "camelCase")]
+    pub struct MultiAction<'a, P: Serialize + std::fmt::Debug> {
+        #[serde(flatten)]
+        action: &'a Action<'a, P>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        key: Option<&'a str>,
     }
 
     #[derive(Debug, Serialize, new)]
@@ -0,0 +1,496 @@
+//! Asynchronous AnkiConnect client, built on `reqwest`.
+//!
+//! It has the same surface as [`AnkiConnectClient`](super::AnkiConnectClient), with `async`
+//! methods, and reuses the params and responses of the blocking clients. The futures have to be
+//! polled by a tokio runtime.
+use std::collections::HashMap;
+use std::time::Duration;
+
+use reqwest::{Client, RequestBuilder};
+use serde::{Serialize, de::DeserializeOwned};
+
+use super::{
+    anki_connect_client::params::{Action, Multi, MultiAction, TestConnectionParams},
+    card::CardId,
+    deck::DeckId,
+    decks_client::params as decks_params,
+    error::APIError,
+    model::Model,
+    models_client::params as models_params,
+    note::NoteId,
+    notes_client::{params as notes_params, responses::NoteInfo},
+    params::Params,
+    response::Response,
+    util::escape_search,
+};
+use crate::model::{InternalModelMethods, ModelType};
+
+#[derive(Debug, Clone)]
+pub struct AsyncAnkiConnectClient {
+    client: Client,
+    url: String,
+    key: Option<String>,
+}
+
+#[allow(unused)]
+impl AsyncAnkiConnectClient {
+    pub fn new(url: Option<&str>, port: Option<u32>, key: Option<&str>) -> Self {
+        AsyncAnkiConnectClient {
+            client: Client::builder()
+                .timeout(Duration::from_secs(5))
+                .build()
+                .unwrap_or_default(),
+            url: format!(
+                "{}:{}",
+                url.unwrap_or("http://localhost"),
+                port.unwrap_or(8765)
+            ),
+            key: key.map(str::to_string),
+        }
+    }
+
+    pub fn decks(&self) -> AsyncDecksClient<'_> {
+        AsyncDecksClient(self)
+    }
+
+    pub fn models(&self) -> AsyncModelsClient<'_> {
+        AsyncModelsClient(self)
+    }
+
+    pub fn notes(&self) -> AsyncNotesClient<'_> {
+        AsyncNotesClient(self)
+    }
+
+    async fn send<R>(&self, request: RequestBuilder) -> Result<Response<R>, APIError>
+    where
+        R: DeserializeOwned + std::fmt::Debug,
+    {
+        let response = request
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Response<R>>()
+            .await?;
+        match response.error {
+            Some(err) => Err(APIError::AnkiConnectError(err)),
+            None => Ok(response),
+        }
+    }
+
+    pub async fn request_with_timeout<R, P>(
+        &self,
+        action: &str,
+        params: Option<P>,
+        timeout: Option<u8>,
+    ) -> Result<Response<R>, APIError>
+    where
+        R: DeserializeOwned + std::fmt::Debug,
+        P: Serialize + std::fmt::Debug,
+    {
+        self.send(
+            self.client
+                .post(&self.url)
+                .timeout(Duration::from_secs(timeout.unwrap_or(1).into()))
+                .json(&Params::new(action, params, self.key.as_deref())),
+        )
+        .await
+    }
+
+    pub async fn request<R, P>(
+        &self,
+        action: &str,
+        params: Option<P>,
+    ) -> Result<Response<R>, APIError>
+    where
+        R: DeserializeOwned + std::fmt::Debug,
+        P: Serialize + std::fmt::Debug,
+    {
+        self.send(self.client.post(&self.url).json(&Params::new(
+            action,
+            params,
+            self.key.as_deref(),
+        )))
+        .await
+    }
+
+    pub async fn test_connection(&self) -> Result<bool, APIError> {
+        match self
+            .request_with_timeout::<TestConnectionParams, TestConnectionParams>(
+                "apiReflect",
+                Some(TestConnectionParams::new(
+                    vec!["actions".into()],
+                    vec!["apiReflect".into()],
+                )),
+                Some(1),
+            )
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(err) => match err {
+                APIError::Http { .. } => Ok(false),
+                _ => Err(APIError::FailedConnection(err.to_string())),
+            },
+        }
+    }
+
+    pub async fn multi<P, R>(&self, actions: Vec<&Action<'_, P>>) -> Result<Vec<R>, APIError>
+    where
+        P: Serialize + std::fmt::Debug,
+        R: DeserializeOwned + std::fmt::Debug,
+    {
+        let key = self.key.as_deref();
+        let actions = actions
+            .into_iter()
+            .map(|action| MultiAction::new(action, key))
+            .collect();
+        self.request::<Vec<R>, _>("multi", Some(Multi::new(actions)))
+            .await
+            .map(|response| response.result.unwrap())
+    }
+}
+
+impl Default for AsyncAnkiConnectClient {
+    fn default() -> Self {
+        Self::new(None, None, None)
+    }
+}
+
+/// Asynchronous version of [`DecksClient`](super::decks_client::DecksClient).
+#[derive(Debug, Clone)]
+pub struct AsyncDecksClient<'a>(pub &'a AsyncAnkiConnectClient);
+
+#[allow(unused)]
+impl AsyncDecksClient<'_> {
+    /// Gets the complete list of deck names for the current user.
+    pub async fn deck_names(&self) -> Result<Vec<String>, APIError> {
+        let response: Response<Vec<String>> = self.0.request("deckNames", None::<()>).await?;
+        Ok(response.result.unwrap())
+    }
+
+    /// Gets the complete list of deck names and their respective IDs for the current user.
+    pub async fn deck_names_and_ids(&self) -> Result<HashMap<String, DeckId>, APIError> {
+        let response: Response<HashMap<String, DeckId>> =
+            self.0.request("deckNamesAndIds", None::<()>).await?;
+        Ok(response.result.unwrap())
+    }
+
+    pub async fn find_deck_id_by_name(&self, name: &str) -> Result<DeckId, APIError> {
+        let decks = self.deck_names_and_ids().await?;
+        match decks.get(name) {
+            Some(id) => Ok(id.to_owned()),
+            None => Err(APIError::DeckNotFound),
+        }
+    }
+
+    /// Accepts an array of card IDs and returns an object with each deck name as a key, and its value an array of the given cards which belong to it.
+    pub async fn get_decks(
+        &self,
+        cards: &Vec<&CardId>,
+    ) -> Result<HashMap<String, Vec<CardId>>, APIError> {
+        self.0
+            .request("getDecks", Some(decks_params::GetDecks::new(cards)))
+            .await
+            .map(|result| result.result.unwrap())
+    }
+
+    /// Create a new empty deck. Will not overwrite a deck that exists with the same name.
+    pub async fn create_deck(&self, deck_name: &str) -> Result<DeckId, APIError> {
+        self.0
+            .request("createDeck", Some(decks_params::CreateDeck::new(deck_name)))
+            .await
+            .map(|response| response.result.unwrap())
+    }
+
+    pub async fn find_or_create_deck(&self, deck_name: &str) -> Result<DeckId, APIError> {
+        match self.find_deck_id_by_name(deck_name).await {
+            Err(_) => self.create_deck(deck_name).await,
+            deck => deck,
+        }
+    }
+
+    /// Moves cards with the given IDs to a different deck, creating the deck if it doesn't exist yet.
+    pub async fn change_deck(&self, cards: Vec<&CardId>, deck: &str) -> Result<bool, APIError> {
+        self.0
+            .request::<Option<()>, _>(
+                "changeDeck",
+                Some(decks_params::ChangeDeck::new(cards, deck)),
+            )
+            .await
+            .map(|_| true)
+    }
+
+    /// Deletes decks with the given names.
+    pub async fn delete_decks(&self, decks: Vec<&str>) -> Result<bool, APIError> {
+        self.0
+            .request::<Option<()>, _>(
+                "deleteDecks",
+                Some(decks_params::DeleteDecks::new(
+                    decks.into_iter().map(|name| name.to_string()).collect(),
+                    true,
+                )),
+            )
+            .await
+            .map(|_| true)
+    }
+
+    /// Deletes the deck with the given name.
+    pub async fn delete(&self, deck_name: &str) -> Result<bool, APIError> {
+        self.delete_decks(vec![deck_name]).await
+    }
+}
+
+/// Asynchronous version of [`NotesClient`](super::notes_client::NotesClient).
+#[derive(Debug, Clone)]
+pub struct AsyncNotesClient<'a>(pub &'a AsyncAnkiConnectClient);
+
+#[allow(unused)]
+impl AsyncNotesClient<'_> {
+    /// Returns an array of note IDs for a given query.
+    pub async fn find_notes(&self, query: &str) -> Result<Vec<NoteId>, APIError> {
+        let response: Response<Vec<NoteId>> = self
+            .0
+            .request("findNotes", Some(notes_params::FindNotes::new(query)))
+            .await?;
+        Ok(response.result.unwrap())
+    }
+
+    /// Gets ids of all notes stored in a deck based on deck_name.
+    pub async fn find_notes_ids_by_deck_name(
+        &self,
+        deck_name: &str,
+    ) -> Result<Vec<NoteId>, APIError> {
+        self.find_notes(&format!("deck:\"{}\"", escape_search(deck_name)))
+            .await
+    }
+
+    /// Creates a note using the given deck and model, with the provided field values and tags.
+    /// See [`NotesClient::add_note`](super::notes_client::NotesClient::add_note).
+    pub async fn add_note(
+        &self,
+        add_note: notes_params::AddNoteNote<'_>,
+    ) -> Result<NoteId, APIError> {
+        self.0
+            .request("addNote", Some(notes_params::AddNote::new(add_note)))
+            .await
+            .map(|result| result.result.unwrap())
+    }
+
+    /// Creates multiple notes using the given deck and model, with the provided field values and
+    /// tags. Returns an array of identifiers of the created notes.
+    pub async fn add_notes(
+        &self,
+        notes: notes_params::AddNotes<'_>,
+    ) -> Result<Vec<NoteId>, APIError> {
+        self.0
+            .request("addNotes", Some(notes))
+            .await
+            .map(|result| result.result.unwrap())
+    }
+
+    pub async fn add_notes_convenience(
+        &self,
+        deck_name: &str,
+        model_name: &str,
+        notes: Vec<&ModelType<'_>>,
+    ) -> Result<Vec<NoteId>, APIError> {
+        self.add_notes(notes_params::AddNotes::new(
+            notes
+                .iter()
+                .map(|note| note.to_add_note(deck_name, model_name))
+                .collect(),
+        ))
+        .await
+    }
+
+    /// Modify the fields of an existing note.
+    pub async fn update_note_fields(
+        &self,
+        note: notes_params::UpdateNoteFields<'_>,
+    ) -> Result<bool, APIError> {
+        self.0
+            .request::<(), _>("updateNoteFields", Some(note))
+            .await
+            .map(|_| true)
+    }
+
+    pub async fn update_note(
+        &self,
+        params: notes_params::UpdateNoteFields<'_>,
+    ) -> Result<bool, APIError> {
+        self.0
+            .request::<(), _>("updateNote", Some(params))
+            .await
+            .map(|_| true)
+    }
+
+    /// Changes the model of an existing note, while keeping its cards and their review history.
+    pub async fn update_note_model(
+        &self,
+        params: notes_params::UpdateNoteModel<'_>,
+    ) -> Result<bool, APIError> {
+        self.0
+            .request::<(), _>("updateNoteModel", Some(params))
+            .await
+            .map(|_| true)
+    }
+
+    pub async fn update_note_from_model_type(
+        &self,
+        id: &NoteId,
+        note: &ModelType<'_>,
+        tags: Option<&Vec<&str>>,
+    ) -> Result<bool, APIError> {
+        self.update_note(notes_params::UpdateNoteFields::new(
+            notes_params::UpdateNoteFieldsNote::new(
+                id,
+                note.get_fields(),
+                note.get_audio(),
+                note.get_video(),
+                note.get_picture(),
+                tags,
+            ),
+        ))
+        .await
+    }
+
+    pub async fn notes_info_by_id(&self, ids: &Vec<NoteId>) -> Result<Vec<NoteInfo>, APIError> {
+        self.0
+            .request("notesInfo", Some(notes_params::NotesInfoIds::new(ids)))
+            .await
+            .map(|response| response.result.unwrap())
+    }
+
+    pub async fn notes_info_by_query(&self, query: &str) -> Result<Vec<NoteInfo>, APIError> {
+        self.0
+            .request("notesInfo", Some(notes_params::NotesInfoQuery::new(query)))
+            .await
+            .map(|response| response.result.unwrap())
+    }
+
+    /// Gets ids of all notes stored in a deck based on deck_name.
+    pub async fn get_notes_by_deck_name(&self, deck_name: &str) -> Result<Vec<NoteInfo>, APIError> {
+        self.notes_info_by_query(&format!("deck:\"{}\"", escape_search(deck_name)))
+            .await
+    }
+
+    /// Deletes notes with the given ids. If a note has several cards associated with it, all associated cards will be deleted.
+    pub async fn delete_notes(&self, notes: &Vec<&NoteId>) -> Result<bool, APIError> {
+        self.0
+            .request::<(), _>("deleteNotes", Some(notes_params::DeleteNotes::new(notes)))
+            .await
+            .map(|_| true)
+    }
+}
+
+/// Asynchronous version of [`ModelsClient`](super::models_client::ModelsClient).
+#[derive(Debug, Clone)]
+pub struct AsyncModelsClient<'a>(pub &'a AsyncAnkiConnectClient);
+
+#[allow(unused)]
+impl AsyncModelsClient<'_> {
+    pub async fn update_model_styling(
+        &self,
+        model_name: &str,
+        css: &str,
+    ) -> Result<Response<Option<()>>, APIError> {
+        self.0
+            .request(
+                "updateModelStyling",
+                Some(models_params::UpdateModelStyling::new(
+                    models_params::UpdateModelStylingModel::new(model_name, css),
+                )),
+            )
+            .await
+    }
+
+    /// Modify the templates of an existing model by name. Only the specified cards and specified
+    /// sides will be modified.
+    pub async fn update_model_templates(
+        &self,
+        templates: models_params::UpdateModelTemplates<'_>,
+    ) -> Result<Response<Option<()>>, APIError> {
+        self.0
+            .request("updateModelTemplates", Some(templates))
+            .await
+    }
+
+    /// Creates a new field within a given model, by default at the end of the field list.
+    pub async fn model_field_add(
+        &self,
+        model_name: &str,
+        field_name: &str,
+        index: Option<usize>,
+    ) -> Result<Response<Option<()>>, APIError> {
+        self.0
+            .request(
+                "modelFieldAdd",
+                Some(models_params::ModelFieldAdd::new(
+                    model_name, field_name, index,
+                )),
+            )
+            .await
+    }
+
+    /// Adds a template to an existing model by name.
+    pub async fn model_template_add(
+        &self,
+        model_name: &str,
+        name: &str,
+        front: &str,
+        back: &str,
+    ) -> Result<Response<Option<()>>, APIError> {
+        self.0
+            .request(
+                "modelTemplateAdd",
+                Some(models_params::ModelTemplateAdd::new(
+                    model_name,
+                    HashMap::from([("Name", name), ("Front", front), ("Back", back)]),
+                )),
+            )
+            .await
+    }
+
+    /// Removes a template from an existing model. All the cards generated by the template are
+    /// deleted as well.
+    pub async fn model_template_remove(
+        &self,
+        model_name: &str,
+        template_name: &str,
+    ) -> Result<Response<Option<()>>, APIError> {
+        self.0
+            .request(
+                "modelTemplateRemove",
+                Some(models_params::ModelTemplateRemove::new(
+                    model_name,
+                    template_name,
+                )),
+            )
+            .await
+    }
+
+    pub async fn get_all_names(&self) -> Result<Vec<String>, APIError> {
+        let response: Response<Vec<String>> = self.0.request("modelNames", None::<()>).await?;
+        Ok(response.result.unwrap())
+    }
+
+    pub async fn find_by_name(&self, model_names: Vec<&str>) -> Result<Vec<Model>, APIError> {
+        let models = self
+            .0
+            .request::<Vec<Model>, _>(
+                "findModelsByName",
+                Some(models_params::FindModelsByNameParams::new(model_names)),
+            )
+            .await?;
+        Ok(models.result.unwrap())
+    }
+
+    pub async fn create_model(
+        &self,
+        model: models_params::CreateModel<'_>,
+    ) -> Result<Model, APIError> {
+        self.0
+            .request("createModel", Some(model))
+            .await
+            .map(|result| result.result.unwrap())
+    }
+}
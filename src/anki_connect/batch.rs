@@ -0,0 +1,137 @@
+use std::thread::sleep;
+use std::time::Duration;
+
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use super::anki_connect_client::{params::Action, response::BasicResponse};
+use super::{AnkiConnectClient, error::APIError};
+
+/// Default number of actions sent in a single `multi` request.
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+/// Default number of times a request is sent again when it could not reach AnkiConnect.
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// Settings of [`AnkiConnectClient::multi_batched`].
+#[derive(Debug, Clone, Copy)]
+pub struct BatchOptions {
+    /// Maximum number of actions sent in a single `multi` request.
+    pub batch_size: usize,
+    /// Number of times a request that could not reach AnkiConnect is sent again.
+    pub retries: u32,
+    /// Delay before the first retry, it is doubled after every attempt.
+    pub backoff: Duration,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        BatchOptions {
+            batch_size: DEFAULT_BATCH_SIZE,
+            retries: DEFAULT_RETRIES,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+impl AnkiConnectClient {
+    /// Sends the actions with `multi` requests of at most `batch_size` actions, and returns one
+    /// response per action, in the same order as the actions.
+    ///
+    /// Requests that could not reach AnkiConnect are retried with an exponential backoff. Requests
+    /// that timed out or failed while Anki was busy are not sent again, as their actions may have
+    /// been applied already and adding the same notes twice creates duplicates. A batch
+    /// rejected as a whole by AnkiConnect is split in two halves that are sent separately, until
+    /// the failing action is isolated, so that the other actions of the batch are still applied.
+    /// Any other error, like a response that cannot be decoded, is returned in the response of
+    /// each action of the batch without sending it again.
+    ///
+    /// `on_batch` is called with the number of actions after every batch.
+    pub fn multi_batched<P>(
+        &self,
+        actions: &[Action<P>],
+        options: &BatchOptions,
+        on_batch: &dyn Fn(usize),
+    ) -> Vec<BasicResponse>
+    where
+        P: Serialize + std::fmt::Debug,
+    {
+        let mut responses = Vec::with_capacity(actions.len());
+        for batch in actions.chunks(options.batch_size.max(1)) {
+            self.send_batch(batch, options, &mut responses);
+            on_batch(batch.len());
+        }
+        responses
+    }
+
+    fn send_batch<P>(
+        &self,
+        batch: &[Action<P>],
+        options: &BatchOptions,
+        responses: &mut Vec<BasicResponse>,
+    ) where
+        P: Serialize + std::fmt::Debug,
+    {
+        let result = self
+            .multi_with_retries(batch, options)
+            .and_then(|batch_responses| {
+                if batch_responses.len() == batch.len() {
+                    Ok(batch_responses)
+                } else {
+                    Err(APIError::UnknownError(format!(
+                        "expected {} responses, got {}",
+                        batch.len(),
+                        batch_responses.len()
+                    )))
+                }
+            });
+        match result {
+            Ok(batch_responses) => responses.extend(batch_responses),
+            // Only a batch rejected by AnkiConnect is known not to be applied, other errors may
+            // happen after its actions were applied
+            Err(err @ APIError::AnkiConnectError(_)) if batch.len() > 1 => {
+                debug!(
+                    "Splitting batch of {} actions after error: {}",
+                    batch.len(),
+                    err
+                );
+                let (left, right) = batch.split_at(batch.len() / 2);
+                self.send_batch(left, options, responses);
+                self.send_batch(right, options, responses);
+            }
+            Err(err) => {
+                let error = err.to_string();
+                responses.extend(
+                    batch
+                        .iter()
+                        .map(|_| BasicResponse::new(None, Some(error.clone()))),
+                );
+            }
+        }
+    }
+
+    fn multi_with_retries<P>(
+        &self,
+        batch: &[Action<P>],
+        options: &BatchOptions,
+    ) -> Result<Vec<BasicResponse>, APIError>
+    where
+        P: Serialize + std::fmt::Debug,
+    {
+        let mut delay = options.backoff;
+        let mut attempt = 0;
+        loop {
+            match self.multi::<P, BasicResponse>(batch.iter().collect()) {
+                Err(err) if err.is_unsent() && attempt < options.retries => {
+                    attempt += 1;
+                    warn!(
+                        "AnkiConnect request failed ({}), retrying in {:?} ({}/{}).",
+                        err, delay, attempt, options.retries
+                    );
+                    sleep(delay);
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::HashSet;
+
+use derive_new::new;
+
+use super::{AnkiConnectClient, client::ClientBehavior, error::APIError};
+
+/// Version of the AnkiConnect API sent with every request. Older versions of the add-on are
+/// rejected by [`AnkiConnectClient::capabilities`].
+pub const API_VERSION: u8 = 6;
+
+/// Version and actions of the installed AnkiConnect add-on.
+#[derive(Debug, Clone, new)]
+pub struct Capabilities {
+    pub version: u8,
+    pub actions: HashSet<String>,
+}
+
+impl Capabilities {
+    pub fn supports(&self, action: &str) -> bool {
+        self.actions.contains(action)
+    }
+
+    /// Fails with the first action that the add-on doesn't support.
+    pub fn require(&self, actions: &[&str]) -> Result<(), APIError> {
+        match actions.iter().find(|action| !self.supports(action)) {
+            Some(action) => Err(APIError::UnsupportedAction {
+                action: action.to_string(),
+                version: self.version,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl AnkiConnectClient {
+    /// Queries the API version and the available actions of AnkiConnect. The result is cached
+    /// and shared between the clones of the client.
+    pub fn capabilities(&self) -> Result<&Capabilities, APIError> {
+        if let Some(capabilities) = self.capabilities_cache().get() {
+            return Ok(capabilities);
+        }
+        let version: u8 = self
+            .request("version", None::<()>)?
+            .result
+            .unwrap_or_default();
+        if version < API_VERSION {
+            return Err(APIError::UnsupportedVersion {
+                version,
+                required: API_VERSION,
+            });
+        }
+        // Without a list of actions, `apiReflect` returns every action of the add-on
+        let reflected: params::ApiReflectResult = self
+            .request(
+                "apiReflect",
+                Some(params::ApiReflect::new(vec!["actions"], None)),
+            )?
+            .result
+            .unwrap_or_default();
+        Ok(self
+            .capabilities_cache()
+            .get_or_init(|| Capabilities::new(version, reflected.actions.into_iter().collect())))
+    }
+
+    /// Fails with a clear error when the installed add-on is too old for one of the actions.
+    pub fn require_actions(&self, actions: &[&str]) -> Result<(), APIError> {
+        self.capabilities()?.require(actions)
+    }
+}
+
+pub mod params {
+    use derive_new::new;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Serialize, new)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ApiReflect<'a> {
+        scopes: Vec<&'a str>,
+        actions: Option<Vec<&'a str>>,
+    }
+
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ApiReflectResult {
+        #[serde(default)]
+        pub actions: Vec<String>,
+    }
+}
@@ -1,10 +1,78 @@
 // From Anki repo
 
 use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
 
+use super::note::NoteId;
 use crate::new_id_type;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Card {}
+/// A card with its scheduling state, as returned by `cardsInfo`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Card {
+    pub card_id: CardId,
+    pub note: NoteId,
+    pub deck_name: String,
+    pub model_name: String,
+    /// Index of the template of the card in the model.
+    pub ord: u16,
+    #[serde(rename = "type")]
+    pub kind: CardType,
+    pub queue: CardQueue,
+    /// Position of new cards, day of review cards relative to the creation of the collection, or
+    /// unix time of learning cards.
+    pub due: i64,
+    /// Interval in days, negative values are seconds for cards in learning.
+    pub interval: i64,
+    /// Ease factor in permille, 2500 for an ease of 250%.
+    pub factor: u32,
+    pub reps: u32,
+    pub lapses: u32,
+    /// Learning steps left before the card graduates.
+    pub left: u32,
+    #[serde(rename = "mod")]
+    pub mtime: u64,
+    pub question: String,
+    pub answer: String,
+    #[serde(default)]
+    pub flags: u8,
+}
+
+impl Card {
+    pub fn is_suspended(&self) -> bool {
+        self.queue == CardQueue::Suspended
+    }
+
+    /// Ease factor as a ratio, 2.5 for a factor of 2500.
+    pub fn ease(&self) -> f64 {
+        f64::from(self.factor) / 1000.0
+    }
+}
 
 new_id_type!(CardId, i64);
+
+#[derive(Serialize_repr, Deserialize_repr, Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u8)]
+pub enum CardType {
+    New = 0,
+    Learn = 1,
+    Review = 2,
+    Relearn = 3,
+}
+
+#[derive(Serialize_repr, Deserialize_repr, Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(i8)]
+pub enum CardQueue {
+    /// Buried manually by the user.
+    UserBuried = -3,
+    /// Buried automatically, e.g. sibling of a reviewed card.
+    SchedBuried = -2,
+    Suspended = -1,
+    New = 0,
+    /// Learning cards due within the day, `due` is a unix time.
+    Learn = 1,
+    Review = 2,
+    /// Learning cards due on a later day, `due` is a day.
+    DayLearn = 3,
+    PreviewRepeat = 4,
+}
@@ -1,17 +1,133 @@
-use super::AnkiConnectClient;
+use super::{
+    AnkiConnectClient,
+    card::{Card, CardId},
+    client::ClientBehavior,
+    error::APIError,
+    note::NoteId,
+    util::escape_search,
+};
 
 #[derive(Debug, Clone)]
 pub struct CardsClient<'a>(pub &'a AnkiConnectClient);
 
-impl CardsClient<'_> {}
+impl CardsClient<'_> {
+    /// Returns an array of card IDs for a given query.
+    pub fn find_cards(&self, query: &str) -> Result<Vec<CardId>, APIError> {
+        self.0
+            .request("findCards", Some(params::FindCards::new(query)))
+            .map(|response| response.result.unwrap_or_default())
+    }
+
+    /// Gets ids of all cards stored in a deck and its subdecks based on deck_name.
+    pub fn find_cards_ids_by_deck_name(&self, deck_name: &str) -> Result<Vec<CardId>, APIError> {
+        self.find_cards(&format!("deck:\"{}\"", escape_search(deck_name)))
+    }
+
+    /// Suspends the cards with the given IDs. Returns true if at least one card wasn't already
+    /// suspended.
+    pub fn suspend(&self, cards: &[&CardId]) -> Result<bool, APIError> {
+        self.0
+            .request("suspend", Some(params::Cards::new(cards)))
+            .map(|response| response.result.unwrap_or_default())
+    }
+
+    /// Unsuspends the cards with the given IDs. Returns true if at least one card was suspended.
+    pub fn unsuspend(&self, cards: &[&CardId]) -> Result<bool, APIError> {
+        self.0
+            .request("unsuspend", Some(params::Cards::new(cards)))
+            .map(|response| response.result.unwrap_or_default())
+    }
+
+    /// Returns whether each card is suspended, `None` for cards that don't exist.
+    pub fn are_suspended(&self, cards: &[&CardId]) -> Result<Vec<Option<bool>>, APIError> {
+        self.0
+            .request("areSuspended", Some(params::Cards::new(cards)))
+            .map(|response| response.result.unwrap_or_default())
+    }
+
+    /// Returns the deck, model, content and scheduling state of each card.
+    pub fn cards_info(&self, cards: &[&CardId]) -> Result<Vec<Card>, APIError> {
+        self.0
+            .request("cardsInfo", Some(params::Cards::new(cards)))
+            .map(|response| response.result.unwrap_or_default())
+    }
+
+    /// Returns the IDs of the notes of the cards, without duplicates and in no particular order.
+    pub fn cards_to_notes(&self, cards: &[&CardId]) -> Result<Vec<NoteId>, APIError> {
+        self.0
+            .request("cardsToNotes", Some(params::Cards::new(cards)))
+            .map(|response| response.result.unwrap_or_default())
+    }
+
+    /// Reschedules the cards as review cards due in the given number of days, e.g. `"0"` for
+    /// today, `"3-7"` for a random day in the range, or `"1!"` to also set the interval to 1 day.
+    pub fn set_due_date(&self, cards: &[&CardId], days: &str) -> Result<bool, APIError> {
+        self.0
+            .request("setDueDate", Some(params::SetDueDate::new(cards, days)))
+            .map(|response| response.result.unwrap_or_default())
+    }
+
+    /// Resets the cards to new cards, their review history is kept.
+    pub fn forget_cards(&self, cards: &[&CardId]) -> Result<bool, APIError> {
+        self.0
+            .request::<Option<()>, _>("forgetCards", Some(params::Cards::new(cards)))
+            .map(|_| true)
+    }
+
+    /// Answers the cards as if they were reviewed in Anki. Returns whether each card was found.
+    pub fn answer_cards(&self, answers: &[params::CardAnswer]) -> Result<Vec<bool>, APIError> {
+        self.0
+            .request("answerCards", Some(params::AnswerCards::new(answers)))
+            .map(|response| response.result.unwrap_or_default())
+    }
+}
 
 pub mod params {
     use derive_new::new;
     use serde::Serialize;
+    use serde_repr::Serialize_repr;
+
+    use crate::anki_connect::card::CardId;
+
+    #[derive(Debug, Serialize, new)]
+    #[serde(rename_all = "camelCase")]
+    pub struct FindCards<'a> {
+        query: &'a str,
+    }
 
     #[derive(Debug, Serialize, new)]
     #[serde(rename_all = "camelCase")]
-    pub struct ActionPrams {
-        var: String,
+    pub struct Cards<'a> {
+        cards: &'a [&'a CardId],
+    }
+
+    #[derive(Debug, Serialize, new)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SetDueDate<'a> {
+        cards: &'a [&'a CardId],
+        days: &'a str,
+    }
+
+    #[derive(Debug, Serialize, new)]
+    #[serde(rename_all = "camelCase")]
+    pub struct AnswerCards<'a> {
+        answers: &'a [CardAnswer],
+    }
+
+    #[derive(Debug, Serialize, new)]
+    #[serde(rename_all = "camelCase")]
+    pub struct CardAnswer {
+        card_id: CardId,
+        ease: Ease,
+    }
+
+    /// Answer button pressed for a card.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr)]
+    #[repr(u8)]
+    pub enum Ease {
+        Again = 1,
+        Hard = 2,
+        Good = 3,
+        Easy = 4,
     }
 }
@@ -1,4 +1,7 @@
+use std::sync::OnceLock;
+
 use super::AnkiConnectClient;
+use super::capabilities::Capabilities;
 use super::{error::APIError, response::Response};
 use enum_dispatch::enum_dispatch;
 use serde::{Serialize, de::DeserializeOwned};
@@ -18,13 +21,21 @@ pub trait ClientBehavior {
     where
         R: DeserializeOwned + std::fmt::Debug,
         P: Serialize + std::fmt::Debug;
+    /// API key sent with every request.
+    fn key(&self) -> Option<&str>;
+    /// Capabilities of AnkiConnect, queried once and shared between the clones of the client.
+    fn capabilities_cache(&self) -> &OnceLock<Capabilities>;
 }
 
+mod recording_client;
+mod replay_client;
 #[cfg(feature = "reqwest_blocking")]
 mod reqwest_client;
 #[cfg(feature = "ureq_blocking")]
 mod ureq_client;
 
+pub use recording_client::{RecordingClient, TraceEntry};
+pub use replay_client::ReplayClient;
 #[cfg(feature = "reqwest_blocking")]
 pub use reqwest_client::ReqwestClient;
 #[cfg(feature = "ureq_blocking")]
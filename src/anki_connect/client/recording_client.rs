@@ -0,0 +1,134 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use tracing::warn;
+
+use super::ClientBehavior;
+use crate::anki_connect::{
+    AnkiConnectClient, capabilities::Capabilities, error::APIError, response::Response,
+};
+
+/// Request sent to AnkiConnect together with its response, one per line of a trace file. The API
+/// key is removed from the parameters, see [`without_key`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceEntry {
+    pub action: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+    #[serde(default)]
+    pub result: Option<Value>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Client writing every request and its response to a JSONL trace, which can be served again by
+/// a [`ReplayClient`](super::ReplayClient).
+///
+/// Requests that fail before AnkiConnect answers, e.g. because Anki is not running, are not
+/// recorded.
+#[derive(Debug, Clone)]
+pub struct RecordingClient {
+    inner: Box<AnkiConnectClient>,
+    file: Arc<Mutex<File>>,
+}
+
+impl RecordingClient {
+    pub fn new(inner: AnkiConnectClient, path: &Path) -> std::io::Result<Self> {
+        Ok(RecordingClient {
+            inner: Box::new(inner),
+            file: Arc::new(Mutex::new(File::create(path)?)),
+        })
+    }
+
+    /// Appends the request and its response to the trace, and decodes the result.
+    fn record<R>(
+        &self,
+        action: &str,
+        params: Option<Value>,
+        response: Result<Response<Value>, APIError>,
+    ) -> Result<Response<R>, APIError>
+    where
+        R: DeserializeOwned + std::fmt::Debug,
+    {
+        let (result, error) = match &response {
+            Ok(response) => (response.result.clone(), None),
+            Err(APIError::AnkiConnectError(err)) => (None, Some(err.clone())),
+            Err(_) => return response.and_then(Response::decode),
+        };
+        let entry = TraceEntry {
+            action: action.to_string(),
+            params: without_key(params),
+            result,
+            error,
+        };
+        let written = serde_json::to_string(&entry)
+            .map_err(std::io::Error::other)
+            .and_then(|line| writeln!(self.file.lock().unwrap(), "{}", line));
+        if let Err(err) = written {
+            warn!("Failed to record the '{}' request: {}", action, err);
+        }
+        response.and_then(Response::decode)
+    }
+}
+
+/// Removes the API key from the parameters, including the key `multi` adds to each of its actions.
+pub(super) fn without_key(mut params: Option<Value>) -> Option<Value> {
+    if let Some(Value::Object(object)) = &mut params {
+        object.remove("key");
+        if let Some(Value::Array(actions)) = object.get_mut("actions") {
+            for action in actions.iter_mut().filter_map(Value::as_object_mut) {
+                action.remove("key");
+            }
+        }
+    }
+    params
+}
+
+/// Serializes the parameters once, so that the same value is sent and recorded.
+fn to_value<P: Serialize>(params: Option<P>) -> Result<Option<Value>, APIError> {
+    params
+        .map(serde_json::to_value)
+        .transpose()
+        .map_err(|err| APIError::UnknownError(err.to_string()))
+}
+
+impl ClientBehavior for RecordingClient {
+    fn request_with_timeout<R, P>(
+        &self,
+        action: &str,
+        params: Option<P>,
+        timeout: Option<u8>,
+    ) -> Result<Response<R>, APIError>
+    where
+        R: DeserializeOwned + std::fmt::Debug,
+        P: Serialize + std::fmt::Debug,
+    {
+        let params = to_value(params)?;
+        let response = self
+            .inner
+            .request_with_timeout::<Value, _>(action, params.clone(), timeout);
+        self.record(action, params, response)
+    }
+
+    fn request<R, P>(&self, action: &str, params: Option<P>) -> Result<Response<R>, APIError>
+    where
+        R: DeserializeOwned + std::fmt::Debug,
+        P: Serialize + std::fmt::Debug,
+    {
+        let params = to_value(params)?;
+        let response = self.inner.request::<Value, _>(action, params.clone());
+        self.record(action, params, response)
+    }
+
+    fn key(&self) -> Option<&str> {
+        self.inner.key()
+    }
+
+    fn capabilities_cache(&self) -> &OnceLock<Capabilities> {
+        self.inner.capabilities_cache()
+    }
+}
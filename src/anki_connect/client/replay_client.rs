@@ -0,0 +1,120 @@
+use std::fs::read_to_string;
+use std::io::{Error as IOError, ErrorKind};
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use tracing::debug;
+
+use super::recording_client::without_key;
+use super::{ClientBehavior, TraceEntry};
+use crate::anki_connect::{capabilities::Capabilities, error::APIError, response::Response};
+
+/// Client answering requests from a trace written by a
+/// [`RecordingClient`](super::RecordingClient), without connecting to AnkiConnect.
+///
+/// A request is answered with the first unused response recorded for the same action and
+/// parameters, a request whose parameters changed is an error. With `loose`, the first unused
+/// response of the same action is used instead, e.g. for parameters listing items in a different
+/// order.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayClient {
+    entries: Arc<Mutex<Vec<TraceEntry>>>,
+    capabilities: Arc<OnceLock<Capabilities>>,
+    loose: bool,
+}
+
+impl ReplayClient {
+    pub fn open(path: &Path, loose: bool) -> std::io::Result<Self> {
+        let entries = read_to_string(path)?
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line).map_err(|err| {
+                    IOError::new(
+                        ErrorKind::InvalidData,
+                        format!("line {} of '{}': {}", index + 1, path.display(), err),
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ReplayClient {
+            entries: Arc::new(Mutex::new(entries)),
+            capabilities: Arc::default(),
+            loose,
+        })
+    }
+
+    fn replay<R, P>(&self, action: &str, params: Option<P>) -> Result<Response<R>, APIError>
+    where
+        R: DeserializeOwned + std::fmt::Debug,
+        P: Serialize + std::fmt::Debug,
+    {
+        let params = without_key(
+            params
+                .map(serde_json::to_value)
+                .transpose()
+                .map_err(|err| APIError::UnknownError(err.to_string()))?,
+        );
+        let mut entries = self.entries.lock().unwrap();
+        let index = match entries
+            .iter()
+            .position(|entry| entry.action == action && entry.params == params)
+        {
+            Some(index) => index,
+            None => {
+                let index = entries
+                    .iter()
+                    .position(|entry| entry.action == action)
+                    .ok_or_else(|| APIError::NotRecorded(action.to_string()))?;
+                if !self.loose {
+                    return Err(APIError::ReplayMismatch(action.to_string()));
+                }
+                debug!("No response recorded for these '{}' parameters", action);
+                index
+            }
+        };
+        let entry = entries.remove(index);
+        match entry.error {
+            Some(err) => Err(APIError::AnkiConnectError(err)),
+            None => Response::<Value> {
+                result: entry.result,
+                error: None,
+            }
+            .decode(),
+        }
+    }
+}
+
+impl ClientBehavior for ReplayClient {
+    fn request_with_timeout<R, P>(
+        &self,
+        action: &str,
+        params: Option<P>,
+        _timeout: Option<u8>,
+    ) -> Result<Response<R>, APIError>
+    where
+        R: DeserializeOwned + std::fmt::Debug,
+        P: Serialize + std::fmt::Debug,
+    {
+        self.replay(action, params)
+    }
+
+    fn request<R, P>(&self, action: &str, params: Option<P>) -> Result<Response<R>, APIError>
+    where
+        R: DeserializeOwned + std::fmt::Debug,
+        P: Serialize + std::fmt::Debug,
+    {
+        self.replay(action, params)
+    }
+
+    fn key(&self) -> Option<&str> {
+        None
+    }
+
+    fn capabilities_cache(&self) -> &OnceLock<Capabilities> {
+        &self.capabilities
+    }
+}
@@ -1,27 +1,47 @@
 use serde::{Serialize, de::DeserializeOwned};
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
-use ureq::Agent;
+
+use reqwest::blocking::{Client, RequestBuilder};
 
 use super::ClientBehavior;
-use crate::anki_connect::{error::APIError, params::Params, response::Response};
+use crate::anki_connect::{
+    capabilities::Capabilities, error::APIError, params::Params, response::Response,
+};
 
 #[derive(Debug, Clone)]
 pub struct ReqwestClient {
-    agent: Agent,
+    client: Client,
     url: String,
+    key: Option<String>,
+    capabilities: Arc<OnceLock<Capabilities>>,
 }
+
 impl ReqwestClient {
-    pub fn new(url: Option<&str>, port: Option<u32>) -> Self {
-        let config = Agent::config_builder()
-            .timeout_global(Some(Duration::from_secs(5)))
-            .build();
+    pub fn new(url: Option<&str>, port: Option<u32>, key: Option<&str>) -> Self {
         ReqwestClient {
-            agent: config.into(),
+            client: Client::builder()
+                .timeout(Duration::from_secs(5))
+                .build()
+                .unwrap_or_default(),
             url: format!(
                 "{}:{}",
                 url.unwrap_or("http://localhost"),
                 port.unwrap_or(8765)
             ),
+            key: key.map(str::to_string),
+            capabilities: Arc::default(),
+        }
+    }
+
+    fn send<R>(&self, request: RequestBuilder) -> Result<Response<R>, APIError>
+    where
+        R: DeserializeOwned + std::fmt::Debug,
+    {
+        let response = request.send()?.error_for_status()?.json::<Response<R>>()?;
+        match response.error {
+            Some(err) => Err(APIError::AnkiConnectError(err)),
+            None => Ok(response),
         }
     }
 }
@@ -37,30 +57,12 @@ impl ClientBehavior for ReqwestClient {
         R: DeserializeOwned + std::fmt::Debug,
         P: Serialize + std::fmt::Debug,
     {
-        match self
-            .agent
-            .post(&self.url)
-            .config()
-            .timeout_global(Some(Duration::from_secs(timeout.unwrap_or(1).into())))
-            .build()
-            .send_json(Params::new(action, params))
-            .map_err(APIError::UreqError)?
-            .body_mut()
-            .read_json::<Response<R>>()
-        {
-            Ok(response) => {
-                dbg!(&response);
-                if response.error.is_some() {
-                    Err(APIError::AnkiConnectError(response.error.unwrap()))
-                } else {
-                    Ok(response)
-                }
-            }
-            Err(err) => {
-                dbg!(&err);
-                Err(APIError::UnknownError(err.to_string()))
-            }
-        }
+        self.send(
+            self.client
+                .post(&self.url)
+                .timeout(Duration::from_secs(timeout.unwrap_or(1).into()))
+                .json(&Params::new(action, params, self.key.as_deref())),
+        )
     }
 
     fn request<R, P>(&self, action: &str, params: Option<P>) -> Result<Response<R>, APIError>
@@ -68,38 +70,24 @@ impl ClientBehavior for ReqwestClient {
         R: DeserializeOwned + std::fmt::Debug,
         P: Serialize + std::fmt::Debug,
     {
-        match self
-            .agent
-            .post(&self.url)
-            .send_json(Params::new(action, params))
-            .map_err(APIError::UreqError)?
-            .body_mut()
-            .read_json::<Response<R>>()
-        {
-            Ok(response) => {
-                // trace!("{}", &response);
-                if response.error.is_some() {
-                    Err(APIError::AnkiConnectError(response.error.unwrap()))
-                } else {
-                    Ok(response)
-                }
-            }
-            Err(err) => {
-                // trace!("{}", &err);
-                Err(APIError::UnknownError(err.to_string()))
-            }
-        }
+        self.send(self.client.post(&self.url).json(&Params::new(
+            action,
+            params,
+            self.key.as_deref(),
+        )))
+    }
+
+    fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    fn capabilities_cache(&self) -> &OnceLock<Capabilities> {
+        &self.capabilities
     }
 }
 
 impl Default for ReqwestClient {
     fn default() -> Self {
-        let config = Agent::config_builder()
-            .timeout_global(Some(Duration::from_secs(5)))
-            .build();
-        ReqwestClient {
-            agent: config.into(),
-            url: format!("{}:{}", "http://localhost", 8765),
-        }
+        Self::new(None, None, None)
     }
 }
@@ -1,7 +1,10 @@
 use super::ClientBehavior;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
-use crate::anki_connect::{error::APIError, params::Params, response::Response};
+use crate::anki_connect::{
+    capabilities::Capabilities, error::APIError, params::Params, response::Response,
+};
 use serde::{Serialize, de::DeserializeOwned};
 use ureq::Agent;
 
@@ -9,10 +12,12 @@ use ureq::Agent;
 pub struct UreqClient {
     agent: Agent,
     url: String,
+    key: Option<String>,
+    capabilities: Arc<OnceLock<Capabilities>>,
 }
 
 impl UreqClient {
-    pub fn new(url: Option<&str>, port: Option<u32>) -> Self {
+    pub fn new(url: Option<&str>, port: Option<u32>, key: Option<&str>) -> Self {
         let config = Agent::config_builder()
             .timeout_global(Some(Duration::from_secs(5)))
             .build();
@@ -23,6 +28,8 @@ impl UreqClient {
                 url.unwrap_or("http://localhost"),
                 port.unwrap_or(8765)
             ),
+            key: key.map(str::to_string),
+            capabilities: Arc::default(),
         }
     }
 }
@@ -44,8 +51,8 @@ impl ClientBehavior for UreqClient {
             .config()
             .timeout_global(Some(Duration::from_secs(timeout.unwrap_or(1).into())))
             .build()
-            .send_json(Params::new(action, params))
-            .map_err(APIError::UreqError)?
+            .send_json(Params::new(action, params, self.key.as_deref()))
+            .map_err(APIError::from)?
             .body_mut()
             .read_json::<Response<R>>()
         {
@@ -72,8 +79,8 @@ impl ClientBehavior for UreqClient {
         match self
             .agent
             .post(&self.url)
-            .send_json(Params::new(action, params))
-            .map_err(APIError::UreqError)?
+            .send_json(Params::new(action, params, self.key.as_deref()))
+            .map_err(APIError::from)?
             .body_mut()
             .read_json::<Response<R>>()
         {
@@ -92,6 +99,14 @@ impl ClientBehavior for UreqClient {
             }
         }
     }
+
+    fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    fn capabilities_cache(&self) -> &OnceLock<Capabilities> {
+        &self.capabilities
+    }
 }
 
 impl Default for UreqClient {
@@ -102,6 +117,8 @@ impl Default for UreqClient {
         UreqClient {
             agent: config.into(),
             url: format!("{}:{}", "http://localhost", 8765),
+            key: None,
+            capabilities: Arc::default(),
         }
     }
 }
@@ -1,12 +1,102 @@
-use strum::Display;
 use thiserror::Error;
 
-#[derive(Debug, Display, Error)]
+/// Reason why an HTTP request to AnkiConnect failed, independent of the HTTP client that is
+/// compiled in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpErrorKind {
+    /// The connection to AnkiConnect could not be opened, e.g. Anki is not running, so nothing was
+    /// sent.
+    Connection,
+    Timeout,
+    /// AnkiConnect answered with an unexpected HTTP status code.
+    Status(u16),
+    Other,
+}
+
+#[derive(Debug, Error)]
 pub enum APIError {
+    #[error("{0}")]
     AnkiConnectError(String),
+    #[error("cannot connect to AnkiConnect: {0}")]
     FailedConnection(String),
+    #[error("unexpected response: {0}")]
     UnknownError(String),
-    UreqError(#[from] ureq::Error),
+    #[error("HTTP request failed: {message}")]
+    Http {
+        kind: HttpErrorKind,
+        message: String,
+    },
+    #[error("deck not found")]
     DeckNotFound,
+    #[error(
+        "AnkiConnect API version {version} is not supported, version {required} or later is required, update the AnkiConnect add-on"
+    )]
+    UnsupportedVersion { version: u8, required: u8 },
+    #[error(
+        "the installed AnkiConnect add-on (API version {version}) doesn't support `{action}`, update it to the latest version"
+    )]
+    UnsupportedAction { action: String, version: u8 },
+    #[error("no response to `{0}` left in the replayed trace")]
+    NotRecorded(String),
+    #[error(
+        "the parameters of `{0}` differ from the replayed trace, pass --replay-loose to ignore them"
+    )]
+    ReplayMismatch(String),
     // ModelNotFound(String),
 }
+
+impl APIError {
+    /// Whether the request failed before reaching AnkiConnect, so that sending it again cannot
+    /// apply its actions twice. A request that timed out may have been applied already.
+    pub fn is_unsent(&self) -> bool {
+        matches!(
+            self,
+            APIError::Http {
+                kind: HttpErrorKind::Connection,
+                ..
+            }
+        )
+    }
+}
+
+#[cfg(feature = "ureq_blocking")]
+impl From<ureq::Error> for APIError {
+    fn from(err: ureq::Error) -> Self {
+        let kind = match &err {
+            ureq::Error::StatusCode(code) => HttpErrorKind::Status(*code),
+            ureq::Error::Timeout(_) => HttpErrorKind::Timeout,
+            // Other IO errors may happen after the request was sent, e.g. while reading the response
+            ureq::Error::ConnectionFailed | ureq::Error::HostNotFound => HttpErrorKind::Connection,
+            ureq::Error::Io(io) if io.kind() == std::io::ErrorKind::ConnectionRefused => {
+                HttpErrorKind::Connection
+            }
+            _ => HttpErrorKind::Other,
+        };
+        APIError::Http {
+            kind,
+            message: err.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Error> for APIError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_decode() {
+            return APIError::UnknownError(err.to_string());
+        }
+        let kind = if err.is_timeout() {
+            HttpErrorKind::Timeout
+        } else if err.is_connect() {
+            HttpErrorKind::Connection
+        } else if let Some(status) = err.status() {
+            HttpErrorKind::Status(status.as_u16())
+        } else {
+            HttpErrorKind::Other
+        };
+        APIError::Http {
+            kind,
+            message: err.to_string(),
+        }
+    }
+}
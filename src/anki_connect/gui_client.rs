@@ -0,0 +1,49 @@
+use super::{
+    AnkiConnectClient, card::CardId, client::ClientBehavior, error::APIError, note::NoteId,
+};
+
+#[derive(Debug, Clone)]
+pub struct GuiClient<'a>(pub &'a AnkiConnectClient);
+
+#[allow(unused)]
+impl GuiClient<'_> {
+    /// Opens the card browser with the given search query. Returns the ids of the cards found.
+    pub fn gui_browse(&self, query: &str) -> Result<Vec<CardId>, APIError> {
+        self.0
+            .request("guiBrowse", Some(params::GuiBrowse::new(query)))
+            .map(|response| response.result.unwrap_or_default())
+    }
+
+    /// Opens the editor of the note, with its cards listed in a preview.
+    pub fn gui_edit_note(&self, note: &NoteId) -> Result<(), APIError> {
+        self.0
+            .request::<Option<()>, _>("guiEditNote", Some(params::GuiEditNote::new(note)))
+            .map(|_| ())
+    }
+
+    /// Gets the ids of the notes selected in the card browser, empty if it is not open.
+    pub fn gui_selected_notes(&self) -> Result<Vec<NoteId>, APIError> {
+        self.0
+            .request("guiSelectedNotes", None::<()>)
+            .map(|response| response.result.unwrap_or_default())
+    }
+}
+
+pub mod params {
+    use derive_new::new;
+    use serde::Serialize;
+
+    use crate::anki_connect::note::NoteId;
+
+    #[derive(Debug, Serialize, new)]
+    #[serde(rename_all = "camelCase")]
+    pub struct GuiBrowse<'a> {
+        query: &'a str,
+    }
+
+    #[derive(Debug, Serialize, new)]
+    #[serde(rename_all = "camelCase")]
+    pub struct GuiEditNote<'a> {
+        note: &'a NoteId,
+    }
+}
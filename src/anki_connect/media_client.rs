@@ -0,0 +1,185 @@
+use std::path::{Path, PathBuf};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+
+use super::{AnkiConnectClient, client::ClientBehavior, error::APIError};
+
+#[derive(Debug, Clone)]
+pub struct MediaClient<'a>(pub &'a AnkiConnectClient);
+
+impl MediaClient<'_> {
+    /// Stores a file in the media folder of Anki. Returns the name under which the file was
+    /// stored, which differs from `filename` when the file was renamed to avoid a collision.
+    pub fn store_file(
+        &self,
+        filename: &str,
+        source: params::MediaSource,
+        on_collision: params::OnCollision,
+    ) -> Result<String, APIError> {
+        if on_collision == params::OnCollision::Keep && self.exists(filename)? {
+            return Ok(filename.to_string());
+        }
+        self.0
+            .request(
+                "storeMediaFile",
+                Some(params::StoreMediaFile::new(
+                    filename,
+                    source,
+                    on_collision == params::OnCollision::Replace,
+                )),
+            )
+            .map(|response| response.result.unwrap_or_default())
+    }
+
+    /// Stores the file at the given path. The path is read by Anki, it has to be accessible from
+    /// the machine running Anki, use [`MediaClient::store_data`] otherwise.
+    pub fn store_path(
+        &self,
+        filename: &str,
+        path: &Path,
+        on_collision: params::OnCollision,
+    ) -> Result<String, APIError> {
+        self.store_file(
+            filename,
+            params::MediaSource::Path(path.to_string_lossy().as_ref()),
+            on_collision,
+        )
+    }
+
+    /// Stores the given content, e.g. read from a file of the vault.
+    pub fn store_data(
+        &self,
+        filename: &str,
+        data: &[u8],
+        on_collision: params::OnCollision,
+    ) -> Result<String, APIError> {
+        self.store_file(
+            filename,
+            params::MediaSource::Data(&STANDARD.encode(data)),
+            on_collision,
+        )
+    }
+
+    /// Stores the file downloaded by Anki from the given URL.
+    pub fn store_url(
+        &self,
+        filename: &str,
+        url: &str,
+        on_collision: params::OnCollision,
+    ) -> Result<String, APIError> {
+        self.store_file(filename, params::MediaSource::Url(url), on_collision)
+    }
+
+    /// Returns the content of the file, or `None` if it doesn't exist.
+    pub fn retrieve_file(&self, filename: &str) -> Result<Option<Vec<u8>>, APIError> {
+        // AnkiConnect returns `false` for missing files
+        let response: serde_json::Value = self
+            .0
+            .request(
+                "retrieveMediaFile",
+                Some(params::MediaFileName::new(filename)),
+            )?
+            .result
+            .unwrap_or_default();
+        match response.as_str() {
+            Some(data) => STANDARD
+                .decode(data)
+                .map(Some)
+                .map_err(|err| APIError::UnknownError(err.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Gets the names of the media files matching the pattern, where `*` matches any characters.
+    pub fn get_media_files_names(&self, pattern: &str) -> Result<Vec<String>, APIError> {
+        self.0
+            .request(
+                "getMediaFilesNames",
+                Some(params::GetMediaFilesNames::new(pattern)),
+            )
+            .map(|response| response.result.unwrap_or_default())
+    }
+
+    /// Checks whether a file with exactly this name exists in the media folder.
+    pub fn exists(&self, filename: &str) -> Result<bool, APIError> {
+        // Wildcards in the name are matched literally
+        let pattern: String = filename
+            .chars()
+            .map(|c| match c {
+                '*' | '?' | '[' => format!("[{}]", c),
+                c => c.to_string(),
+            })
+            .collect();
+        Ok(self
+            .get_media_files_names(&pattern)?
+            .iter()
+            .any(|name| name == filename))
+    }
+
+    /// Gets the full path to the media folder of the current profile.
+    pub fn get_media_dir_path(&self) -> Result<PathBuf, APIError> {
+        self.0
+            .request::<PathBuf, _>("getMediaDirPath", None::<()>)
+            .map(|response| response.result.unwrap_or_default())
+    }
+
+    /// Deletes the file from the media folder, it is moved to the media trash of Anki.
+    pub fn delete_file(&self, filename: &str) -> Result<bool, APIError> {
+        self.0
+            .request::<Option<()>, _>(
+                "deleteMediaFile",
+                Some(params::MediaFileName::new(filename)),
+            )
+            .map(|_| true)
+    }
+}
+
+pub mod params {
+    use derive_new::new;
+    use serde::Serialize;
+
+    /// Content of a stored media file, serialized as the `path`, `data` or `url` parameter.
+    #[derive(Debug, Serialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum MediaSource<'a> {
+        /// Path to the file, read by Anki.
+        Path(&'a str),
+        /// Base64 encoded content of the file.
+        Data(&'a str),
+        /// URL from which Anki downloads the file.
+        Url(&'a str),
+    }
+
+    /// What to do when a file with the same name already exists in the media folder.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub enum OnCollision {
+        /// Replace the existing file, the default of AnkiConnect.
+        #[default]
+        Replace,
+        /// Store the file under a new name if its content differs from the existing file.
+        Rename,
+        /// Keep the existing file and don't store the new one.
+        Keep,
+    }
+
+    #[derive(Debug, Serialize, new)]
+    #[serde(rename_all = "camelCase")]
+    pub struct StoreMediaFile<'a> {
+        filename: &'a str,
+        #[serde(flatten)]
+        source: MediaSource<'a>,
+        delete_existing: bool,
+    }
+
+    #[derive(Debug, Serialize, new)]
+    #[serde(rename_all = "camelCase")]
+    pub struct MediaFileName<'a> {
+        filename: &'a str,
+    }
+
+    #[derive(Debug, Serialize, new)]
+    #[serde(rename_all = "camelCase")]
+    pub struct GetMediaFilesNames<'a> {
+        pattern: &'a str,
+    }
+}
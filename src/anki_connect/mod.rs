@@ -1,16 +1,23 @@
 pub(crate) mod anki_connect_client;
-pub(crate) mod card;
-pub(crate) mod cards_client;
+#[cfg(feature = "reqwest_async")]
+pub mod async_client;
+pub(crate) mod batch;
+pub(crate) mod capabilities;
+pub mod card;
+pub mod cards_client;
 mod client;
 pub(crate) mod deck;
 pub(crate) mod decks_client;
-pub(crate) mod error;
+pub mod error;
+pub(crate) mod gui_client;
+pub mod media_client;
 pub(crate) mod model;
 pub(crate) mod models_client;
-pub(crate) mod note;
+pub(crate) mod multi_builder;
+pub mod note;
 pub(crate) mod notes_client;
 mod params;
 pub(crate) mod response;
-mod util;
+pub(crate) mod util;
 
 pub use anki_connect_client::AnkiConnectClient;
@@ -43,6 +43,19 @@ pub struct Model {
     other: HashMap<String, Value>,
 }
 
+impl Model {
+    pub fn field_names(&self) -> Vec<&str> {
+        self.flds.iter().map(|field| field.name.as_str()).collect()
+    }
+
+    pub fn template_names(&self) -> Vec<&str> {
+        self.tmpls
+            .iter()
+            .map(|template| template.name.as_str())
+            .collect()
+    }
+}
+
 new_id_type!(ModelId, i64);
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use super::{
     AnkiConnectClient, client::ClientBehavior, error::APIError, model::Model, response::Response,
 };
@@ -19,6 +21,61 @@ impl ModelsClient<'_> {
         )
     }
 
+    /// Modify the templates of an existing model by name. Only the specified cards and specified
+    /// sides will be modified. If an existing card or side is not included in the request, it will
+    /// be left unchanged.
+    pub fn update_model_templates(
+        &self,
+        templates: params::UpdateModelTemplates,
+    ) -> Result<Response<Option<()>>, APIError> {
+        self.0.request("updateModelTemplates", Some(templates))
+    }
+
+    /// Creates a new field within a given model. Optionally, the index value can be provided,
+    /// which works exactly the same as the index in `modelFieldReposition`. By default, the field
+    /// is added to the end of the field list.
+    pub fn model_field_add(
+        &self,
+        model_name: &str,
+        field_name: &str,
+        index: Option<usize>,
+    ) -> Result<Response<Option<()>>, APIError> {
+        self.0.request(
+            "modelFieldAdd",
+            Some(params::ModelFieldAdd::new(model_name, field_name, index)),
+        )
+    }
+
+    /// Adds a template to an existing model by name.
+    pub fn model_template_add(
+        &self,
+        model_name: &str,
+        name: &str,
+        front: &str,
+        back: &str,
+    ) -> Result<Response<Option<()>>, APIError> {
+        self.0.request(
+            "modelTemplateAdd",
+            Some(params::ModelTemplateAdd::new(
+                model_name,
+                HashMap::from([("Name", name), ("Front", front), ("Back", back)]),
+            )),
+        )
+    }
+
+    /// Removes a template from an existing model. All the cards generated by the template are
+    /// deleted as well.
+    pub fn model_template_remove(
+        &self,
+        model_name: &str,
+        template_name: &str,
+    ) -> Result<Response<Option<()>>, APIError> {
+        self.0.request(
+            "modelTemplateRemove",
+            Some(params::ModelTemplateRemove::new(model_name, template_name)),
+        )
+    }
+
     pub fn get_all_names(&self) -> Result<Vec<String>, APIError> {
         let response: Response<Vec<String>> = self.0.request("modelNames", None::<()>)?;
         Ok(response.result.unwrap())
@@ -56,6 +113,40 @@ pub mod params {
         css: &'a str,
     }
 
+    #[derive(Debug, Serialize, new)]
+    pub struct UpdateModelTemplates<'a> {
+        model: UpdateModelTemplatesModel<'a>,
+    }
+
+    #[derive(Debug, Serialize, new)]
+    pub struct UpdateModelTemplatesModel<'a> {
+        name: &'a str,
+        templates: HashMap<String, HashMap<&'a str, String>>,
+    }
+
+    #[derive(Debug, Serialize, new)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ModelFieldAdd<'a> {
+        model_name: &'a str,
+        field_name: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        index: Option<usize>,
+    }
+
+    #[derive(Debug, Serialize, new)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ModelTemplateAdd<'a> {
+        model_name: &'a str,
+        template: HashMap<&'a str, &'a str>,
+    }
+
+    #[derive(Debug, Serialize, new)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ModelTemplateRemove<'a> {
+        model_name: &'a str,
+        template_name: &'a str,
+    }
+
     #[derive(Debug, Serialize, new)]
     #[serde(rename_all = "camelCase")]
     pub struct FindModelsByNameParams<'a> {
@@ -0,0 +1,106 @@
+use std::marker::PhantomData;
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use super::anki_connect_client::{params::Action, response::BasicResponse};
+use super::batch::BatchOptions;
+use super::capabilities::API_VERSION;
+use super::{AnkiConnectClient, error::APIError};
+
+/// Builds a `multi` request from actions with different parameter and result types.
+///
+/// Each pushed action returns a [`MultiHandle`], used to decode its own result once the request
+/// was sent. Actions are applied by AnkiConnect in the order they were pushed.
+#[derive(Debug)]
+pub struct MultiBuilder<'a> {
+    client: &'a AnkiConnectClient,
+    actions: Vec<(&'a str, serde_json::Value)>,
+}
+
+/// Position of an action in a [`MultiBuilder`], together with the type of its result.
+#[derive(Debug)]
+pub struct MultiHandle<R> {
+    index: usize,
+    _result: PhantomData<fn() -> R>,
+}
+
+impl<R> Clone for MultiHandle<R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<R> Copy for MultiHandle<R> {}
+
+/// Responses of the actions of a [`MultiBuilder`], in the order they were pushed.
+#[derive(Debug)]
+pub struct MultiResults {
+    responses: Vec<BasicResponse>,
+}
+
+impl AnkiConnectClient {
+    pub fn multi_builder(&self) -> MultiBuilder<'_> {
+        MultiBuilder {
+            client: self,
+            actions: Vec::new(),
+        }
+    }
+}
+
+#[allow(unused)]
+impl<'a> MultiBuilder<'a> {
+    /// Adds an action to the request, its result is decoded as `R`.
+    pub fn push<P, R>(&mut self, action: &'a str, params: &P) -> Result<MultiHandle<R>, APIError>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+    {
+        let params =
+            serde_json::to_value(params).map_err(|err| APIError::UnknownError(err.to_string()))?;
+        self.actions.push((action, params));
+        Ok(MultiHandle {
+            index: self.actions.len() - 1,
+            _result: PhantomData,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Sends the actions with the default [`BatchOptions`].
+    pub fn send(self) -> MultiResults {
+        self.send_batched(&BatchOptions::default(), &|_| ())
+    }
+
+    /// Sends the actions with [`AnkiConnectClient::multi_batched`], so large requests are split in
+    /// batches, retried and isolated in the same way.
+    pub fn send_batched(self, options: &BatchOptions, on_batch: &dyn Fn(usize)) -> MultiResults {
+        let actions: Vec<Action<serde_json::Value>> = self
+            .actions
+            .iter()
+            .map(|(action, params)| Action::new(action, API_VERSION, params))
+            .collect();
+        MultiResults {
+            responses: self.client.multi_batched(&actions, options, on_batch),
+        }
+    }
+}
+
+impl MultiResults {
+    /// Decodes the result of the action, or returns the error AnkiConnect returned for it.
+    pub fn get<R: DeserializeOwned>(&self, handle: MultiHandle<R>) -> Result<R, APIError> {
+        let response = self.responses.get(handle.index).ok_or_else(|| {
+            APIError::UnknownError(format!("no response for action {}", handle.index))
+        })?;
+        if let Some(err) = &response.error {
+            return Err(APIError::AnkiConnectError(err.clone()));
+        }
+        serde_json::from_value(response.result.clone().unwrap_or_default())
+            .map_err(|err| APIError::UnknownError(err.to_string()))
+    }
+}
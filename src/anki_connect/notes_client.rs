@@ -2,6 +2,7 @@ use crate::model::{InternalModelMethods, ModelType};
 
 use super::{
     AnkiConnectClient, client::ClientBehavior, error::APIError, note::NoteId, response::Response,
+    util::escape_search,
 };
 
 use rayon::prelude::*;
@@ -20,7 +21,7 @@ impl NotesClient<'_> {
 
     /// Gets ids of all notes stored in a deck based on deck_name.
     pub fn find_notes_ids_by_deck_name(&self, deck_name: &str) -> Result<Vec<NoteId>, APIError> {
-        let notes = self.find_notes(&format!("deck:{}", deck_name))?;
+        let notes = self.find_notes(&format!("deck:\"{}\"", escape_search(deck_name)))?;
 
         Ok(notes)
     }
@@ -100,6 +101,15 @@ impl NotesClient<'_> {
             .map(|_| true)
     }
 
+    /// Changes the model of an existing note, while keeping its cards and their review history.
+    /// The fields of the new model are set from the given values, and the tags of the note are
+    /// replaced with the given ones.
+    pub fn update_note_model(&self, params: params::UpdateNoteModel) -> Result<bool, APIError> {
+        self.0
+            .request::<(), _>("updateNoteModel", Some(params))
+            .map(|_| true)
+    }
+
     pub fn update_note_from_model_type(
         &self,
         id: &NoteId,
@@ -138,7 +148,7 @@ impl NotesClient<'_> {
         &self,
         deck_name: &str,
     ) -> Result<Vec<responses::NoteInfo>, APIError> {
-        self.notes_info_by_query(&format!("deck:{}", deck_name))
+        self.notes_info_by_query(&format!("deck:\"{}\"", escape_search(deck_name)))
     }
 
     /// Deletes notes with the given ids. If a note has several cards associated with it, all associated cards will be deleted.
@@ -282,6 +292,22 @@ pub mod params {
         #[serde(skip_serializing_if = "Option::is_none")]
         tags: Option<Vec<&'a str>>,
     }
+    // updateNoteModel
+    #[derive(Debug, Serialize, new)]
+    #[serde(rename_all = "camelCase")]
+    pub struct UpdateNoteModel<'a> {
+        note: UpdateNoteModelNote<'a>,
+    }
+
+    #[derive(Debug, Serialize, new)]
+    #[serde(rename_all = "camelCase")]
+    pub struct UpdateNoteModelNote<'a> {
+        id: &'a NoteId,
+        model_name: &'a str,
+        fields: HashMap<&'a str, &'a str>,
+        tags: Vec<&'a str>,
+    }
+
     // deletNotes
     #[derive(Debug, Serialize, new)]
     #[serde(rename_all = "camelCase")]
@@ -305,14 +331,33 @@ pub mod responses {
         pub markdown_id: String,
         pub operation: NoteOperation,
         pub note_id: NoteId,
-        profile: String,
-        model_name: String,
-        tags: Vec<String>,
-        fields: Vec<(String, String)>,
-        mtime: u64,
+        /// Name of the Anki profile the note was read from.
+        pub profile: String,
+        pub model_name: String,
+        pub tags: Vec<String>,
+        pub fields: Vec<(String, String)>,
+        pub mtime: u64,
         pub cards: Vec<CardId>,
     }
 
+    impl NoteInfo {
+        /// Returns the value of a field by name.
+        pub fn field(&self, name: &str) -> Option<&str> {
+            self.fields
+                .iter()
+                .find(|(field_name, _)| field_name == name)
+                .map(|(_, value)| value.as_str())
+        }
+
+        /// Checks whether the note already contains the given field values, in which case there is
+        /// no need to update it.
+        pub fn is_up_to_date(&self, fields: &HashMap<&str, &str>) -> bool {
+            fields
+                .iter()
+                .all(|(name, value)| self.field(name) == Some(*value))
+        }
+    }
+
     #[derive(Debug, Deserialize)]
     #[serde(rename_all = "camelCase")]
     struct NoteInfoHelper {
@@ -1,19 +1,25 @@
 use serde::Serialize;
 
+use super::capabilities::API_VERSION;
+
 #[derive(Debug, Serialize)]
 pub struct Params<'a, P: Serialize> {
     action: &'a str,
     #[serde(skip_serializing_if = "Option::is_none")]
     params: Option<P>,
     version: u8,
+    /// API key, required when `apiKey` is set in the AnkiConnect config.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<&'a str>,
 }
 
 impl<'a, P: Serialize> Params<'a, P> {
-    pub fn new(action: &'a str, params: Option<P>) -> Self {
+    pub fn new(action: &'a str, params: Option<P>, key: Option<&'a str>) -> Self {
         Params {
             action: action.into(),
             params,
-            version: 6,
+            version: API_VERSION,
+            key,
         }
     }
 }
@@ -1,6 +1,6 @@
 use std::fmt::{Debug, Display};
 
-use serde::Deserialize;
+use serde::{Deserialize, de::DeserializeOwned};
 
 use super::error::APIError;
 
@@ -26,6 +26,21 @@ impl<R: Debug> From<Result<R, APIError>> for Response<R> {
     }
 }
 
+impl Response<serde_json::Value> {
+    /// Decodes the result of a response that was read as JSON.
+    pub fn decode<R: DeserializeOwned + Debug>(self) -> Result<Response<R>, APIError> {
+        let result = self
+            .result
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|err| APIError::UnknownError(err.to_string()))?;
+        Ok(Response {
+            result,
+            error: self.error,
+        })
+    }
+}
+
 impl<R: Debug> Display for Response<R> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", &self)
@@ -18,4 +18,17 @@ where
     Ok(T::deserialize(v).unwrap_or_default())
 }
 
+/// Escapes text for a quoted Anki search term, so that quotes, backslashes and the `*` and `_`
+/// wildcards are matched literally.
+pub fn escape_search(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '"' | '\\' | '*' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
 new_id_type!(Usn, i32);
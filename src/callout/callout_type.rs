@@ -1,94 +1,219 @@
 #![allow(unreachable_patterns)]
 // TODO: fix patterns that are unreachable due to duplicate names in Korean
-use strum::{Display, EnumProperty, EnumString};
+use strum::{Display, EnumIter, EnumProperty, EnumString};
 
-#[derive(Debug, Default, Display, EnumString, EnumProperty, PartialEq, Eq)]
+#[derive(Debug, Default, Display, EnumIter, EnumString, EnumProperty, PartialEq, Eq)]
 #[strum(serialize_all = "kebab-case")]
 pub enum CalloutType {
     // Builtin callouts
-    #[strum(to_string = "abstract", serialize = "개요", props(ko = "개요"))]
+    #[strum(
+        to_string = "abstract",
+        serialize = "개요",
+        props(ko = "개요", icon = "clipboard-list")
+    )]
     Abstract,
-    #[strum(to_string = "attention", serialize = "알림", props(ko = "알림"))]
+    #[strum(
+        to_string = "attention",
+        serialize = "알림",
+        props(ko = "알림", icon = "alert-triangle")
+    )]
     Attention,
-    #[strum(to_string = "bug", serialize = "버그", props(ko = "버그"))]
+    #[strum(
+        to_string = "bug",
+        serialize = "버그",
+        props(ko = "버그", icon = "bug")
+    )]
     Bug,
-    #[strum(to_string = "caution", serialize = "주의", props(ko = "주의"))]
+    #[strum(
+        to_string = "caution",
+        serialize = "주의",
+        props(ko = "주의", icon = "alert-triangle")
+    )]
     Caution,
-    #[strum(to_string = "check", serialize = "확인됨", props(ko = "확인됨"))]
+    #[strum(
+        to_string = "check",
+        serialize = "확인됨",
+        props(ko = "확인됨", icon = "check")
+    )]
     Check,
-    #[strum(to_string = "cite", serialize = "인용", props(ko = "인용"))]
+    #[strum(
+        to_string = "cite",
+        serialize = "인용",
+        props(ko = "인용", icon = "quote")
+    )]
     Cite,
-    #[strum(to_string = "danger", serialize = "위험", props(ko = "위험"))]
+    #[strum(
+        to_string = "danger",
+        serialize = "위험",
+        props(ko = "위험", icon = "zap")
+    )]
     Danger,
-    #[strum(to_string = "done", serialize = "완료", props(ko = "완료"))]
+    #[strum(
+        to_string = "done",
+        serialize = "완료",
+        props(ko = "완료", icon = "check")
+    )]
     Done,
-    #[strum(to_string = "error", serialize = "오류", props(ko = "오류"))]
+    #[strum(
+        to_string = "error",
+        serialize = "오류",
+        props(ko = "오류", icon = "zap")
+    )]
     Error,
-    #[strum(to_string = "example", serialize = "예", props(ko = "예"))]
+    #[strum(
+        to_string = "example",
+        serialize = "예",
+        props(ko = "예", icon = "list")
+    )]
     Example,
-    #[strum(to_string = "fail", serialize = "실패", props(ko = "실패"))]
+    #[strum(to_string = "fail", serialize = "실패", props(ko = "실패", icon = "x"))]
     Fail,
     // TODO: find a better way to distinguish Fail and Failure in korean
-    #[strum(to_string = "failure", serialize = "실패", props(ko = "실패"))]
+    #[strum(
+        to_string = "failure",
+        serialize = "실패",
+        props(ko = "실패", icon = "x")
+    )]
     Failure,
     #[strum(
         to_string = "faq",
         serialize = "자주-묻는-질문",
-        props(ko = "자주-묻는-질문")
+        props(ko = "자주-묻는-질문", icon = "help-circle")
     )]
     Faq,
-    #[strum(to_string = "help", serialize = "도움말", props(ko = "도움말"))]
+    #[strum(
+        to_string = "help",
+        serialize = "도움말",
+        props(ko = "도움말", icon = "help-circle")
+    )]
     Help,
-    #[strum(to_string = "hint", serialize = "힌트", props(ko = "힌트"))]
+    #[strum(
+        to_string = "hint",
+        serialize = "힌트",
+        props(ko = "힌트", icon = "flame")
+    )]
     Hint,
-    #[strum(to_string = "important", serialize = "중요", props(ko = "중요"))]
+    #[strum(
+        to_string = "important",
+        serialize = "중요",
+        props(ko = "중요", icon = "flame")
+    )]
     Important,
-    #[strum(to_string = "info", serialize = "정보", props(ko = "정보"))]
+    #[strum(
+        to_string = "info",
+        serialize = "정보",
+        props(ko = "정보", icon = "info")
+    )]
     Info,
-    #[strum(to_string = "missing", serialize = "누락", props(ko = "누락"))]
+    #[strum(
+        to_string = "missing",
+        serialize = "누락",
+        props(ko = "누락", icon = "x")
+    )]
     Missing,
-    #[strum(to_string = "note", serialize = "노트", props(ko = "노트"))]
+    #[strum(
+        to_string = "note",
+        serialize = "노트",
+        props(ko = "노트", icon = "pencil")
+    )]
     Note,
-    #[strum(to_string = "question", serialize = "질문", props(ko = "질문"))]
+    #[strum(
+        to_string = "question",
+        serialize = "질문",
+        props(ko = "질문", icon = "help-circle")
+    )]
     Question,
-    #[strum(to_string = "quote", serialize = "인용", props(ko = "인용"))]
+    #[strum(
+        to_string = "quote",
+        serialize = "인용",
+        props(ko = "인용", icon = "quote")
+    )]
     Quote,
-    #[strum(to_string = "success", serialize = "성공", props(ko = "성공"))]
+    #[strum(
+        to_string = "success",
+        serialize = "성공",
+        props(ko = "성공", icon = "check")
+    )]
     Success,
-    #[strum(to_string = "summary", serialize = "요약", props(ko = "요약"))]
+    #[strum(
+        to_string = "summary",
+        serialize = "요약",
+        props(ko = "요약", icon = "clipboard-list")
+    )]
     Summary,
-    #[strum(to_string = "tip", serialize = "팁", props(ko = "팁"))]
+    #[strum(to_string = "tip", serialize = "팁", props(ko = "팁", icon = "flame"))]
     Tip,
-    #[strum(to_string = "tldr", serialize = "요약", props(ko = "요약"))]
+    #[strum(
+        to_string = "tldr",
+        serialize = "요약",
+        props(ko = "요약", icon = "clipboard-list")
+    )]
     Tldr,
-    #[strum(to_string = "todo", serialize = "작업", props(ko = "작업"))]
+    #[strum(
+        to_string = "todo",
+        serialize = "작업",
+        props(ko = "작업", icon = "check-circle-2")
+    )]
     Todo,
-    #[strum(to_string = "warning", serialize = "경고", props(ko = "경고"))]
+    #[strum(
+        to_string = "warning",
+        serialize = "경고",
+        props(ko = "경고", icon = "alert-triangle")
+    )]
     Warning,
 
     // Custom callouts
-    #[strum(to_string = "links", serialize = "링크", props(ko = "링크"))]
+    #[strum(
+        to_string = "links",
+        serialize = "링크",
+        props(ko = "링크", icon = "link")
+    )]
     Links,
     #[strum(
         to_string = "example-sentence",
         serialize = "예문-문장",
-        props(ko = "예")
+        props(ko = "예", icon = "list")
     )]
     ExampleSentence,
-    #[strum(to_string = "exception", serialize = "예외", props(ko = "예외"))]
+    #[strum(
+        to_string = "exception",
+        serialize = "예외",
+        props(ko = "예외", icon = "alert-triangle")
+    )]
     Exception,
-    #[strum(to_string = "reference", serialize = "참고", props(ko = "참고"))]
+    #[strum(
+        to_string = "reference",
+        serialize = "참고",
+        props(ko = "참고", icon = "quote")
+    )]
     Reference,
-    #[strum(to_string = "rule", serialize = "규칙", props(ko = "규칙"))]
+    #[strum(
+        to_string = "rule",
+        serialize = "규칙",
+        props(ko = "규칙", icon = "scroll-text")
+    )]
     Rule,
     #[default]
-    #[strum(to_string = "word", serialize = "단어", props(ko = "단어"))]
+    #[strum(
+        to_string = "word",
+        serialize = "단어",
+        props(ko = "단어", icon = "whole-word")
+    )]
     Word,
-    #[strum(to_string = "conjugation", serialize = "활용", props(ko = "활용"))]
+    #[strum(
+        to_string = "conjugation",
+        serialize = "활용",
+        props(ko = "활용", icon = "square-function")
+    )]
     Conjugation,
 }
 
 impl CalloutType {
+    /// Name of the Lucide icon displayed in the title of the callout, matching Obsidian's icons.
+    pub fn icon(&self) -> &'static str {
+        self.get_str("icon").unwrap_or("pencil")
+    }
+
     pub fn get_name(&self, lang_iso: Option<&str>) -> String {
         let default = &self.to_string();
         let name = if let Some(lang) = lang_iso {
@@ -0,0 +1,414 @@
+use htmd::HtmlToMarkdown;
+use htmd::options::{BulletListMarker, Options};
+use regex::Regex;
+use std::collections::VecDeque;
+use std::sync::LazyLock;
+
+use super::Callout;
+use super::callout_type::CalloutType;
+use super::content::CalloutContent;
+use super::try_from::RE_HEADER;
+
+/// Matches the markup generated by [`Callout::to_html`] for sub-callouts.
+static RE_SUB_CALLOUT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?s)<details data-callout="([^"]*)" class="callout"><summary class="callout-title"><div class="callout-icon"></div>(.*?)</summary>(.*?)</details>"#,
+    )
+    .unwrap()
+});
+
+/// Converts HTML to markdown with the list markers used in the callouts.
+static HTML_TO_MARKDOWN: LazyLock<HtmlToMarkdown> = LazyLock::new(|| {
+    HtmlToMarkdown::builder()
+        .options(Options {
+            bullet_list_marker: BulletListMarker::Dash,
+            ul_bullet_spacing: 1,
+            ol_number_spacing: 1,
+            ..Options::default()
+        })
+        .build()
+});
+
+#[derive(Debug)]
+enum HtmlSegment<'h> {
+    Text(&'h str),
+    SubCallout {
+        callout_type: &'h str,
+        header: &'h str,
+        content: &'h str,
+    },
+}
+
+/// Sub-callout as written in the markdown source.
+#[derive(Debug)]
+struct SourceSubCallout<'s> {
+    lines: Vec<&'s str>,
+    callout_type: Option<CalloutType>,
+    /// Whether the sub-callout is part of the HTML sent to Anki, links and empty sub-callouts are
+    /// left out.
+    is_rendered: bool,
+}
+
+/// Converts HTML to markdown lines in the format used inside of callouts, where every line is a
+/// separate paragraph.
+pub fn html_to_markdown(html: &str) -> Vec<String> {
+    let markdown = HTML_TO_MARKDOWN
+        .convert(html)
+        .unwrap_or_else(|_| html.to_string());
+    let mut in_code_block = false;
+    markdown
+        .lines()
+        .filter(|line| {
+            if line.trim_start().starts_with("```") {
+                in_code_block = !in_code_block;
+            }
+            in_code_block || !line.trim().is_empty()
+        })
+        .map(|line| match line.trim_end() {
+            // Horizontal rules are written the same way as in the callouts
+            "* * *" => "---".to_string(),
+            line => line.to_string(),
+        })
+        .collect()
+}
+
+/// Converts HTML to a single line of markdown, as used by callout headers.
+pub fn html_to_markdown_line(html: &str) -> String {
+    html_to_markdown(html).join(" ").trim().to_string()
+}
+
+/// Converts the front field of a note back to the header of its callout. The header is sent to
+/// Anki as markdown, so only a field formatted in Anki's editor is converted from HTML, otherwise
+/// the markdown would be escaped.
+pub fn field_to_header(field: &str) -> String {
+    if field.contains('<') {
+        return html_to_markdown_line(field);
+    }
+    field
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+fn split_html(html: &str) -> Vec<HtmlSegment<'_>> {
+    let mut segments = Vec::new();
+    let mut offset = 0;
+    for caps in RE_SUB_CALLOUT.captures_iter(html) {
+        let full = caps.get(0).unwrap();
+        let text = html[offset..full.start()].trim();
+        if !text.is_empty() {
+            segments.push(HtmlSegment::Text(text));
+        }
+        segments.push(HtmlSegment::SubCallout {
+            callout_type: caps.get(1).unwrap().as_str(),
+            header: caps.get(2).unwrap().as_str(),
+            content: caps.get(3).unwrap().as_str(),
+        });
+        offset = full.end();
+    }
+    let text = html[offset..].trim();
+    if !text.is_empty() {
+        segments.push(HtmlSegment::Text(text));
+    }
+    segments
+}
+
+fn split_sub_callouts<'s>(lines: &[&'s str]) -> VecDeque<SourceSubCallout<'s>> {
+    let mut sub_callouts = VecDeque::new();
+    let mut lines_iter = lines.iter().peekable();
+    while let Some(line) = lines_iter.next() {
+        if !line.starts_with("> > [!") {
+            continue;
+        }
+        let mut sub_lines = vec![*line];
+        while let Some(next) = lines_iter.next_if(|next| next.starts_with("> >")) {
+            sub_lines.push(next);
+        }
+
+        let stripped: Vec<&str> = sub_lines
+            .iter()
+            .map(|line| line.strip_prefix(">").unwrap_or(line).trim())
+            .collect();
+        let callout = Callout::try_from(&stripped).ok();
+        let is_rendered = callout.as_ref().is_some_and(|callout| {
+            let is_empty = match callout.content.as_slice() {
+                [] => true,
+                [CalloutContent::Text(text)] => text.is_empty(),
+                _ => false,
+            };
+            !(is_empty || matches!(callout.callout_type, CalloutType::Links))
+        });
+        sub_callouts.push_back(SourceSubCallout {
+            lines: sub_lines,
+            callout_type: callout.map(|callout| callout.callout_type),
+            is_rendered,
+        });
+    }
+    sub_callouts
+}
+
+/// Replaces the header text of a callout header line, keeping its type, folding and the rest of
+/// the line.
+fn replace_header(line: &str, header: &str) -> String {
+    let caps = RE_HEADER.captures(line);
+    match caps.as_ref().and_then(|caps| caps.get(2)) {
+        Some(old) => format!("{}{}{}", &line[..old.start()], header, &line[old.end()..]),
+        None => {
+            // Insert the header after the type and the folding marker
+            let mut end = line.find(']').map_or(line.len(), |index| index + 1);
+            if line[end..].starts_with(['+', '-']) {
+                end += 1;
+            }
+            let rest = line[end..].trim_start();
+            if rest.is_empty() {
+                format!("{} {}", &line[..end], header)
+            } else {
+                format!("{} {} {}", &line[..end], header, rest)
+            }
+        }
+    }
+}
+
+fn header_text(line: &str) -> &str {
+    RE_HEADER
+        .captures(line)
+        .and_then(|caps| caps.get(2))
+        .map_or("", |header| header.as_str())
+}
+
+fn quote(lines: &[String], depth: usize) -> Vec<String> {
+    let prefix = vec![">"; depth].join(" ");
+    lines
+        .iter()
+        .map(|line| {
+            if line.is_empty() {
+                prefix.clone()
+            } else {
+                format!("{} {}", prefix, line)
+            }
+        })
+        .collect()
+}
+
+/// Rewrites the markdown lines of a callout, from its header line to its `^markdown_id` line,
+/// with the content of the fields of its note in Anki. Only the parts whose field is given are
+/// rewritten, sub-callouts keep their original type names and the ones that are not sent to Anki
+/// (links and empty sub-callouts) are preserved.
+pub fn rewrite_callout(
+    block: &[&str],
+    front_html: Option<&str>,
+    back_html: Option<&str>,
+    header_lang: Option<&str>,
+) -> Vec<String> {
+    let (header_line, rest) = block.split_first().expect("callout block cannot be empty");
+    let (id_line, body) = rest.split_last().unwrap_or((header_line, &[]));
+
+    let mut lines = vec![match front_html {
+        Some(front) => replace_header(header_line, &field_to_header(front)),
+        None => header_line.to_string(),
+    }];
+
+    let Some(back) = back_html else {
+        lines.extend(rest.iter().map(|line| line.to_string()));
+        return lines;
+    };
+
+    // Parts of the header line that are displayed as the first lines of the back of the card
+    let header_items: Vec<String> = RE_HEADER
+        .captures(header_line)
+        .map(|caps| {
+            [caps.get(4), caps.get(3)]
+                .into_iter()
+                .flatten()
+                .map(|item| item.as_str().trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut source_sub_callouts = split_sub_callouts(body);
+    let mut chunks: Vec<Vec<String>> = Vec::new();
+    for (index, segment) in split_html(back).into_iter().enumerate() {
+        match segment {
+            HtmlSegment::Text(text) => {
+                let mut text_lines = html_to_markdown(text);
+                if index == 0 {
+                    for item in &header_items {
+                        if text_lines.first() == Some(item) {
+                            text_lines.remove(0);
+                        }
+                    }
+                }
+                if !text_lines.is_empty() {
+                    chunks.push(quote(&text_lines, 1));
+                }
+            }
+            HtmlSegment::SubCallout {
+                callout_type,
+                header,
+                content,
+            } => {
+                while let Some(source) =
+                    source_sub_callouts.pop_front_if(|source| !source.is_rendered)
+                {
+                    chunks.push(source.lines.iter().map(|line| line.to_string()).collect());
+                }
+
+                let header = html_to_markdown_line(header);
+                let source = source_sub_callouts.pop_front().filter(|source| {
+                    source
+                        .callout_type
+                        .as_ref()
+                        .is_some_and(|source_type| source_type.to_string() == callout_type)
+                });
+                let sub_header_line = match source {
+                    Some(source) => {
+                        let source_header = header_text(source.lines[0]);
+                        let default_header = source
+                            .callout_type
+                            .as_ref()
+                            .map(|source_type| source_type.get_name(header_lang))
+                            .unwrap_or_default();
+                        if header == source_header
+                            || (source_header.is_empty() && header == default_header)
+                        {
+                            source.lines[0].to_string()
+                        } else {
+                            replace_header(source.lines[0], &header)
+                        }
+                    }
+                    None => {
+                        let is_default = callout_type
+                            .parse::<CalloutType>()
+                            .is_ok_and(|new_type| new_type.get_name(header_lang) == header);
+                        if is_default || header.is_empty() {
+                            format!("> > [!{}]", callout_type)
+                        } else {
+                            format!("> > [!{}] {}", callout_type, header)
+                        }
+                    }
+                };
+
+                let mut sub_lines = vec![sub_header_line];
+                sub_lines.extend(quote(&html_to_markdown(content), 2));
+                chunks.push(sub_lines);
+            }
+        }
+    }
+    // Sub-callouts removed in Anki are dropped, while the ones never sent to Anki are kept
+    chunks.extend(
+        source_sub_callouts
+            .into_iter()
+            .filter(|source| !source.is_rendered)
+            .map(|source| source.lines.iter().map(|line| line.to_string()).collect()),
+    );
+
+    for chunk in chunks {
+        if lines.len() > 1 {
+            lines.push(">".to_string());
+        }
+        lines.extend(chunk);
+    }
+    lines.push(">".to_string());
+    lines.push(id_line.to_string());
+    lines
+}
+
+/// Rewrites the callout with the given markdown ID inside of a markdown document, see
+/// [`rewrite_callout`]. Returns `None` if the callout cannot be found.
+pub fn replace_callout(
+    source: &str,
+    markdown_id: &str,
+    front_html: Option<&str>,
+    back_html: Option<&str>,
+    header_lang: Option<&str>,
+) -> Option<String> {
+    let lines: Vec<&str> = source.split('\n').collect();
+    let id_line = format!("> ^{}", markdown_id);
+    let end = lines.iter().position(|line| line.trim_end() == id_line)?;
+    let start = lines[..end]
+        .iter()
+        .rposition(|line| line.starts_with("> [!"))?;
+
+    let mut new_lines: Vec<String> = lines[..start].iter().map(|line| line.to_string()).collect();
+    new_lines.extend(rewrite_callout(
+        &lines[start..=end],
+        front_html,
+        back_html,
+        header_lang,
+    ));
+    new_lines.extend(lines[end + 1..].iter().map(|line| line.to_string()));
+    Some(new_lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{CardDirection, InternalModelMethods, ModelType};
+
+    /// Converts a callout to the fields of its note, and rewrites the callout from these fields.
+    fn round_trip(markdown: &str) -> String {
+        let block: Vec<&str> = markdown.lines().collect();
+        let callout = Callout::try_from(&block).unwrap();
+        let note =
+            ModelType::default().from_callout(&callout, None, "Deck", &CardDirection::all(), None);
+        let fields = note.get_fields();
+        rewrite_callout(&block, Some(fields["Front"]), Some(fields["Back"]), None).join("\n")
+    }
+
+    #[test]
+    fn word_round_trip() {
+        let markdown = "> [!word]+ 질문\n> question\n>\n> ^f765cf";
+        assert_eq!(round_trip(markdown), markdown);
+    }
+
+    #[test]
+    fn rule_with_sub_callouts_round_trip() {
+        let markdown = "> [!rule] VS + 고 있다\n> foo\n>\n> > [!info]\n> > -ing in English.\n>\n> > [!예]\n> > 먹다 -> 먹고 있어요\n>\n> ^tcvgsamwfz";
+        assert_eq!(round_trip(markdown), markdown);
+    }
+
+    #[test]
+    fn bold_text_round_trip() {
+        let markdown = "> [!word] **문제**\n> a **hard** problem\n>\n> ^1dc8ad";
+        assert_eq!(round_trip(markdown), markdown);
+    }
+
+    #[test]
+    fn list_round_trip() {
+        let markdown = "> [!rule] 같이 + 요(V)\n> - first item\n> - second item\n>\n> ^2lae6wzuvx";
+        assert_eq!(round_trip(markdown), markdown);
+    }
+
+    #[test]
+    fn multi_line_field_round_trip() {
+        let markdown = "> [!rule] VS + 자\n> let's do V\n> first line\n> second line\n>\n> > [!예문-문장]\n> > 집에 가자.\n> > Let's go home.\n>\n> ^fabuv3tb-c";
+        assert_eq!(round_trip(markdown), markdown);
+    }
+
+    #[test]
+    fn fields_edited_in_anki_are_converted_to_markdown() {
+        let block = ["> [!word] 문제", "> problem", ">", "> ^1dc8ad"];
+        let lines = rewrite_callout(
+            &block,
+            Some("문제 &amp; 질문"),
+            Some("<p>a <b>hard</b> problem</p><ul><li>one</li><li>two</li></ul>"),
+            None,
+        );
+        assert_eq!(
+            lines,
+            [
+                "> [!word] 문제 & 질문",
+                "> a **hard** problem",
+                "> - one",
+                "> - two",
+                ">",
+                "> ^1dc8ad"
+            ]
+        );
+    }
+}
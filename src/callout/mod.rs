@@ -1,6 +1,7 @@
 pub(crate) mod callout_type;
 pub(crate) mod content;
 pub(crate) mod error;
+pub(crate) mod from_html;
 pub(crate) mod try_from;
 
 use callout_type::CalloutType;
@@ -10,6 +11,7 @@ use rayon::iter::{Either, IntoParallelIterator, ParallelIterator};
 use rayon::prelude::*;
 use std::fmt::Display;
 use std::fs::read_to_string;
+use std::hash::{Hash, Hasher};
 use std::path::Path;
 
 use crate::note_operation::NoteOperation;
@@ -67,6 +69,10 @@ impl Callout {
                 ));
             }
         };
+        Self::extract_callouts_from_str(&content)
+    }
+
+    pub fn extract_callouts_from_str(content: &str) -> ExtractCalloutsResult {
         let blocks: Vec<String> = content
             .split("\n> [!")
             .skip(1)
@@ -107,6 +113,23 @@ impl Callout {
         (callouts, failed).into()
     }
 
+    /// Feeds the parsed content of the callout and of its sub-callouts to a hasher, which is used
+    /// to detect the callouts that changed since the last sync.
+    pub fn hash_content<H: Hasher>(&self, state: &mut H) {
+        self.markdown_id.hash(state);
+        self.callout_type.to_string().hash(state);
+        self.header.hash(state);
+        for item in &self.content {
+            match item {
+                CalloutContent::Text(text) => text.hash(state),
+                CalloutContent::SubCalloutIndex(index) => index.hash(state),
+            }
+        }
+        for sub_callout in &self.sub_callouts {
+            sub_callout.hash_content(state);
+        }
+    }
+
     pub fn content_to_html(&self, header_lang: Option<&str>) -> String {
         if self.content.is_empty() {
             return "".to_string();
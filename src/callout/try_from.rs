@@ -5,7 +5,7 @@ use crate::Callout;
 use regex::Regex;
 use std::sync::LazyLock;
 
-static RE_HEADER: LazyLock<Regex> = LazyLock::new(|| {
+pub(crate) static RE_HEADER: LazyLock<Regex> = LazyLock::new(|| {
     const CJK_CHARACTER_RANGES: &str = concat!(
         r"\u2E80-\u2FD5",
         // r"\u3000-\u303F\u3040-\u309F\u30A0-\u30FF",
@@ -1,6 +1,10 @@
 use std::path::PathBuf;
 
-use clap::{ArgAction, Args, Parser, Subcommand};
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+
+use crate::config::OrphanPolicy;
+use crate::model::CardDirection;
+use crate::state::ConflictPolicy;
 
 #[derive(Debug, Parser)]
 #[command(name = "md2anki")]
@@ -32,39 +36,256 @@ pub enum Commands {
     },
     /// Synchronize notes with Anki using AnkiConnect
     #[command(arg_required_else_help = true)]
-    Sync(SyncArgs),
+    Sync(Box<SyncArgs>),
+    /// Update markdown callouts with the notes edited in Anki since the last sync
+    #[command(arg_required_else_help = true)]
+    Pull(PullArgs),
+    /// Revert the changes made to Anki by the most recent sync
+    #[command(arg_required_else_help = true)]
+    Undo(UndoArgs),
+    /// Show the notes of a markdown file, or of a single callout, in the Anki browser
+    #[command(arg_required_else_help = true)]
+    Open(OpenArgs),
 }
 
-#[derive(Args, Debug)]
+#[derive(Args, Debug, Clone)]
 pub struct SyncArgs {
-    /// Delete existing notes in deck before syncing
+    /// Delete the whole deck, with all of its notes, before syncing. It is not limited by
+    /// `--max-delete-fraction`.
     #[arg(long = "delete")]
     pub delete_existing: bool,
 
+    /// Remove the notes in Anki whose callout no longer exists in the markdown files. Without it
+    /// such notes are only reported.
+    #[arg(long)]
+    pub prune: bool,
+
+    /// How notes are removed by `--prune` [default: delete]
+    #[arg(long, value_enum)]
+    pub orphans: Option<OrphanPolicy>,
+
+    /// Deck to which removed notes are moved with `--orphans trash` [default: "<deck> Trash"]
+    #[arg(long)]
+    pub trash_deck: Option<String>,
+
+    /// Abort the sync when it would remove more than this fraction of the notes in the deck,
+    /// between 0 and 1 [default: 0.25]
+    #[arg(long)]
+    pub max_delete_fraction: Option<f64>,
+
     /// Name of deck to which cards should be added
     #[arg(short, long)]
     pub deck: Option<String>,
 
-    /// The type of model to use among Basic, Word, Rule
-    #[arg(short, long = "model", value_parser=["Basic", "Rule", "Word"], default_value = "Basic")]
+    /// Sync the input directory and deck of a profile defined in the config file
+    #[arg(short, long, conflicts_with = "all_profiles")]
+    pub profile: Option<String>,
+
+    /// Sync every profile defined in the config file, one after the other
+    #[arg(long)]
+    pub all_profiles: bool,
+
+    /// Comma separated list of files and directories, relative to the input directory, that are
+    /// not synced
+    #[arg(long, value_delimiter = ',')]
+    pub exclude: Vec<PathBuf>,
+
+    /// The type of model to use among Basic, Word, Rule [default: Basic]
+    #[arg(short, long = "model", value_parser=["Basic", "Rule", "Word"])]
     pub model_type_name: Option<String>,
 
     /// Name of the card model that should be used for the cards
     #[arg(long)]
     pub model_name: Option<String>,
 
-    /// Path to css file containing card style rules. This is only needed if a new model needs
-    /// to be created. If a model exists, the model style will be updated using the given CSS
-    /// file.
+    /// Path to css file containing card style rules. The rules are added on top of the default
+    /// stylesheet bundled with md2anki, and the model style is updated on every sync.
     #[arg(short, long = "css")]
     pub css_file: Option<PathBuf>,
 
+    /// Don't include the default stylesheet, only the rules from the CSS file are used
+    #[arg(long)]
+    pub no_default_css: bool,
+
     /// 2 letter language code (ISO 639-1) to use for callout names.
     /// Falls back to English (en) if not specified or not supported.
-    #[arg(short = 'l', long = "lang", default_value = "en")]
+    #[arg(short = 'l', long = "lang")]
     pub header_lang: Option<String>,
 
+    /// Path to a `md2anki.toml` config file. Defaults to `md2anki.toml` inside of the input
+    /// directory if it exists. Paths of profiles are relative to the config file.
+    #[arg(long = "config")]
+    pub config_file: Option<PathBuf>,
+
+    /// Locale used by Anki's text to speech, e.g. ko_KR, zh_CN, ja_JP.
+    /// TTS is omitted from the card templates unless a language is configured.
+    #[arg(long)]
+    pub tts_lang: Option<String>,
+
+    /// Comma separated list of TTS voices in order of preference
+    #[arg(long, value_delimiter = ',')]
+    pub tts_voices: Vec<String>,
+
+    /// Comma separated list of fields that should be spoken by TTS [default: Front]
+    #[arg(long, value_delimiter = ',')]
+    pub tts_fields: Vec<String>,
+
+    /// TTS playback speed, 1.0 is the normal speed
+    #[arg(long)]
+    pub tts_speed: Option<f32>,
+
+    /// Omit TTS from the card templates, even if it is configured in the config file
+    #[arg(long, conflicts_with_all = ["tts_lang", "tts_voices", "tts_fields", "tts_speed"])]
+    pub no_tts: bool,
+
+    /// Comma separated list of card directions generated for each note. Templates of directions
+    /// that are not listed are only removed from the model with `--remove-templates`.
+    /// Can be restricted per file with the `md2anki-directions` front matter property.
+    /// [default: recognition,recall,listen]
+    #[arg(long, value_delimiter = ',')]
+    pub directions: Vec<CardDirection>,
+
+    /// Remove the templates of directions that are no longer enabled from the model. This deletes
+    /// their cards and review history in every deck using the model.
+    #[arg(long)]
+    pub remove_templates: bool,
+
+    /// Convert notes that use a different model in Anki to the synced model, keeping their
+    /// cards and review history. Without it such notes are reported and left untouched.
+    /// Changing the model of a note requires a one-way sync with AnkiWeb.
+    #[arg(long)]
+    pub change_model: bool,
+
+    /// What to do with notes that were edited in Anki since the last sync [default: skip]
+    #[arg(long, value_enum)]
+    pub on_conflict: Option<ConflictPolicy>,
+
+    /// Sync every note, instead of skipping the files and notes that didn't change since the
+    /// last sync
+    #[arg(long)]
+    pub full: bool,
+
+    /// Only print the changes that would be made, without writing anything to Anki
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Maximum number of actions sent to AnkiConnect in a single request [default: 500]
+    #[arg(long)]
+    pub batch_size: Option<usize>,
+
+    /// Number of times a request is sent again when the connection to Anki cannot be opened
+    /// [default: 3]
+    #[arg(long)]
+    pub retries: Option<u32>,
+
+    /// Format used to print the changes of a dry run
+    #[arg(long, value_enum, default_value_t = PlanFormat::Table, requires = "dry_run")]
+    pub plan_format: PlanFormat,
+
+    /// Sync the collection with AnkiWeb at the end of a successful sync, like the Sync button of
+    /// Anki. Ignored with `--dry-run`.
+    #[arg(long)]
+    pub anki_sync: bool,
+
+    #[command(flatten)]
+    pub anki_connect: AnkiConnectArgs,
+
+    /// Input path used to search for notes. With `--profile` or `--all-profiles` it is only used
+    /// to find the config file.
+    #[arg(required_unless_present_any = ["profile", "all_profiles"])]
+    pub input_dir: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct PullArgs {
+    /// Name of the deck to which the notes were synced
+    #[arg(short, long)]
+    pub deck: String,
+
+    /// 2 letter language code (ISO 639-1) used for callout names during the sync
+    #[arg(short = 'l', long = "lang", default_value = "en")]
+    pub header_lang: String,
+
+    /// Only print the callouts that would be updated, without changing the markdown files
+    #[arg(long)]
+    pub dry_run: bool,
+
+    #[command(flatten)]
+    pub anki_connect: AnkiConnectArgs,
+
     /// Input path used to search for notes
-    // #[arg()]
     pub input_dir: PathBuf,
 }
+
+#[derive(Args, Debug)]
+pub struct UndoArgs {
+    /// Only print the changes that would be reverted
+    #[arg(long)]
+    pub dry_run: bool,
+
+    #[command(flatten)]
+    pub anki_connect: AnkiConnectArgs,
+
+    /// Input path that was synced
+    pub input_dir: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct OpenArgs {
+    /// Show every note of the deck instead of the notes of a file
+    #[arg(short, long, conflicts_with = "target")]
+    pub deck: Option<String>,
+
+    /// Open the note in the editor instead of the browser, only for a single callout
+    #[arg(long, requires = "target")]
+    pub edit: bool,
+
+    #[command(flatten)]
+    pub anki_connect: AnkiConnectArgs,
+
+    /// Markdown file, optionally followed by the id of a callout, e.g. `words.md#^abcdef`
+    #[arg(required_unless_present = "deck")]
+    pub target: Option<String>,
+}
+
+/// Address of AnkiConnect, they take precedence over the `anki-*` settings of the config file.
+#[derive(Args, Debug, Clone)]
+pub struct AnkiConnectArgs {
+    /// URL of AnkiConnect, without the port [default: http://localhost]
+    #[arg(long, env = "ANKI_CONNECT_URL")]
+    pub anki_url: Option<String>,
+
+    /// Port of AnkiConnect [default: 8765]
+    #[arg(long, env = "ANKI_CONNECT_PORT")]
+    pub anki_port: Option<u32>,
+
+    /// API key, required when `apiKey` is set in the AnkiConnect config
+    #[arg(long, env = "ANKI_CONNECT_KEY", hide_env_values = true)]
+    pub anki_key: Option<String>,
+
+    /// Name of the Anki profile to load before reading from or writing to Anki. Without it the
+    /// profile that is currently open is used. A dry run only checks that it is open.
+    #[arg(long, env = "ANKI_PROFILE")]
+    pub anki_profile: Option<String>,
+
+    /// Write every request sent to AnkiConnect and its response to a JSONL file
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Answer the requests from a file written with --record instead of AnkiConnect
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+
+    /// Answer a request whose parameters differ from the trace with the next response recorded for
+    /// the same action, instead of failing
+    #[arg(long, requires = "replay")]
+    pub replay_loose: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PlanFormat {
+    /// Number of changes per deck, followed by the notes that are moved or deleted
+    Table,
+    /// Every planned change as JSON
+    Json,
+}
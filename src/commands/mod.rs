@@ -1,5 +1,73 @@
 mod obsidian_to_anki;
+mod open;
+mod pull;
 mod sync;
+mod sync_plan;
+mod undo;
 
 pub use obsidian_to_anki::create_markdown_anki_cards_file;
+pub use open::open;
+pub use pull::pull;
 pub use sync::sync;
+pub use undo::undo;
+
+use crate::anki_connect::AnkiConnectClient;
+use crate::cli::AnkiConnectArgs;
+use crate::config::Config;
+use crate::error::M2AnkiError;
+use tracing::info;
+
+/// Creates the client for the AnkiConnect address given on the command line, in the environment
+/// or in the config file, or a client replaying a trace given with `--replay`.
+fn anki_connect_client(
+    args: &AnkiConnectArgs,
+    config: &Config,
+) -> Result<AnkiConnectClient, M2AnkiError> {
+    if let Some(path) = &args.replay {
+        return Ok(AnkiConnectClient::replay(path, args.replay_loose)?);
+    }
+    let client = AnkiConnectClient::new(
+        args.anki_url.as_deref().or(config.anki_url.as_deref()),
+        args.anki_port.or(config.anki_port),
+        args.anki_key.as_deref().or(config.anki_key.as_deref()),
+    );
+    match &args.record {
+        Some(path) => Ok(client.record(path)?),
+        None => Ok(client),
+    }
+}
+
+/// Loads the Anki profile given on the command line, in the environment or in the config file,
+/// and checks that it is open. Returns its name, or `None` when the open profile is used.
+///
+/// A dry run doesn't switch profiles, it only checks that the profile is already open.
+fn load_anki_profile(
+    client: &AnkiConnectClient,
+    args: &AnkiConnectArgs,
+    config: &Config,
+    dry_run: bool,
+) -> Result<Option<String>, M2AnkiError> {
+    let Some(name) = args
+        .anki_profile
+        .as_deref()
+        .or(config.anki_profile.as_deref())
+    else {
+        return Ok(None);
+    };
+    client.require_actions(&["getProfiles", "loadProfile", "getActiveProfile"])?;
+    if !client.get_profiles()?.iter().any(|profile| profile == name) {
+        return Err(M2AnkiError::AnkiProfileNotFound(name.to_string()));
+    }
+    if !dry_run {
+        client.load_profile(name)?;
+    }
+    let active = client.get_active_profile()?;
+    if active != name {
+        return Err(M2AnkiError::AnkiProfileMismatch {
+            expected: name.to_string(),
+            found: active,
+        });
+    }
+    info!("Using the Anki profile '{}'.", name);
+    Ok(Some(name.to_string()))
+}
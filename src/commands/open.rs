@@ -0,0 +1,98 @@
+use super::{anki_connect_client, load_anki_profile};
+use crate::anki_connect::error::APIError;
+use crate::anki_connect::util::escape_search;
+use crate::cli::OpenArgs;
+use crate::config::Config;
+use crate::deck::Deck;
+use crate::error::M2AnkiError;
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+
+/// AnkiConnect actions used to show notes in Anki.
+const OPEN_ACTIONS: &[&str] = &["findNotes", "guiBrowse", "guiEditNote"];
+
+/// Splits `file.md#^id` into the path of the file and the markdown id of the callout.
+fn parse_target(target: &str) -> (PathBuf, Option<&str>) {
+    match target.rsplit_once('#') {
+        Some((path, id)) => (PathBuf::from(path), Some(id.trim_start_matches('^'))),
+        None => (PathBuf::from(target), None),
+    }
+}
+
+/// Search query matching the notes synced from the given callouts.
+fn markdown_ids_query(markdown_ids: &[String]) -> String {
+    markdown_ids
+        .iter()
+        .map(|id| format!("\"MarkdownID:{}\"", escape_search(id)))
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+/// Shows the notes synced from a markdown file, from one of its callouts or from a whole deck in
+/// the Anki browser, or a single note in the editor.
+pub fn open(args: OpenArgs) -> Result<(), M2AnkiError> {
+    let target = args.target.as_deref().map(parse_target);
+    let config_dir = target
+        .as_ref()
+        .and_then(|(path, _)| path.parent())
+        .unwrap_or(Path::new(""));
+    let config = Config::find(None, config_dir)?;
+    let client = anki_connect_client(&args.anki_connect, &config)?;
+    if !client.test_connection().unwrap_or(false) {
+        error!("Cannot connect to Anki. Make sure it is running.");
+        return Err(M2AnkiError::APIError(APIError::FailedConnection(
+            "Cannot connect to Anki. Make sure it is running.".to_string(),
+        )));
+    }
+    load_anki_profile(&client, &args.anki_connect, &config, false)?;
+    client.require_actions(OPEN_ACTIONS)?;
+
+    let query = match (&args.deck, target) {
+        (Some(deck), _) => format!("deck:\"{}\"", escape_search(deck)),
+        (None, Some((path, markdown_id))) => {
+            let markdown_ids: Vec<String> = match markdown_id {
+                Some(id) => vec![id.to_string()],
+                None => Deck::try_from(&path)?
+                    .callouts
+                    .into_iter()
+                    .map(|callout| callout.markdown_id)
+                    .collect(),
+            };
+            let notes = if markdown_ids.is_empty() {
+                Vec::new()
+            } else {
+                client
+                    .notes()
+                    .find_notes(&markdown_ids_query(&markdown_ids))?
+            };
+            if notes.is_empty() {
+                warn!(
+                    "No synced notes found for '{}', run sync first.",
+                    args.target.as_deref().unwrap_or_default()
+                );
+                return Ok(());
+            }
+
+            match notes.as_slice() {
+                [note] if args.edit => {
+                    client.gui().gui_edit_note(note)?;
+                    info!("Opened note {} in the editor.", note.0);
+                    return Ok(());
+                }
+                _ if args.edit => warn!(
+                    "Found {} notes, showing them in the browser instead of the editor.",
+                    notes.len()
+                ),
+                _ => (),
+            }
+            let note_ids: Vec<String> = notes.iter().map(|note| note.0.to_string()).collect();
+            format!("nid:{}", note_ids.join(","))
+        }
+        // Either a deck or a target is required by the arguments
+        (None, None) => unreachable!(),
+    };
+
+    let cards = client.gui().gui_browse(&query)?;
+    info!("Showing {} cards in the Anki browser.", cards.len());
+    Ok(())
+}
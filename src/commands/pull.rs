@@ -0,0 +1,128 @@
+use super::{anki_connect_client, load_anki_profile};
+use crate::anki_connect::error::APIError;
+use crate::anki_connect::notes_client::responses::NoteInfo;
+use crate::callout::from_html::{field_to_header, html_to_markdown, replace_callout};
+use crate::cli::PullArgs;
+use crate::config::Config;
+use crate::deck::Deck;
+use crate::error::M2AnkiError;
+use crate::find_markdown_files::find_markdown_files;
+use crate::progress::{LOOKING_GLASS, REPEAT, SPARKLE, Step};
+use crate::state::SyncState;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs::{read_to_string, write};
+use tracing::{error, info, warn};
+
+/// Rewrites the callouts of the notes that were edited in Anki since the last sync, using the
+/// content of their fields.
+pub fn pull(args: PullArgs) -> Result<(), M2AnkiError> {
+    let input_dir = &args.input_dir;
+    let header_lang = Some(args.header_lang.as_str());
+
+    let mut state = SyncState::load(input_dir)?;
+    if state.notes.is_empty() {
+        warn!(
+            "No synced notes found in '{}', run sync first.",
+            input_dir.to_str().unwrap_or_default()
+        );
+        return Ok(());
+    }
+
+    let mut step = Step::new(1, 4);
+    step.print_step(Some("Connecting to Anki"), Some(LOOKING_GLASS));
+    let config = Config::find(None, input_dir)?;
+    let client = anki_connect_client(&args.anki_connect, &config)?;
+    if !client.test_connection().unwrap_or(false) {
+        error!("Cannot connect to Anki. Make sure it is running.");
+        return Err(M2AnkiError::APIError(APIError::FailedConnection(
+            "Cannot connect to Anki. Make sure it is running.".to_string(),
+        )));
+    }
+    load_anki_profile(&client, &args.anki_connect, &config, args.dry_run)?;
+
+    step.print_step(Some("Finding notes edited in Anki"), Some(LOOKING_GLASS));
+    let anki_notes = client.notes().get_notes_by_deck_name(&args.deck)?;
+    let edited_notes: HashMap<&String, &NoteInfo> = anki_notes
+        .iter()
+        .filter(|note| state.is_conflicted(note))
+        .map(|note| (&note.markdown_id, note))
+        .collect();
+    if edited_notes.is_empty() {
+        info!("No notes were edited in Anki since the last sync.");
+        step.print_step(Some("Done"), Some(SPARKLE));
+        return Ok(());
+    }
+
+    let decks: Vec<Deck> = find_markdown_files(input_dir)
+        .unwrap_or_default()
+        .par_iter()
+        .filter_map(|path| Deck::try_from(path).ok())
+        .filter(|deck| {
+            deck.callouts
+                .iter()
+                .any(|callout| edited_notes.contains_key(&callout.markdown_id))
+        })
+        .collect();
+
+    step.print_step(Some("Updating callouts"), Some(REPEAT));
+    let mut num_pulled = 0;
+    let mut checked_notes: Vec<&NoteInfo> = Vec::with_capacity(edited_notes.len());
+    for deck in &decks {
+        let source_file = deck.source_file.to_str().unwrap_or_default();
+        let mut content = read_to_string(&deck.source_file)?;
+        let mut changed = false;
+
+        for callout in &deck.callouts {
+            let Some(anki_note) = edited_notes.get(&callout.markdown_id) else {
+                continue;
+            };
+            // Fields are compared as markdown, so that HTML reformatted by Anki's editor is not
+            // considered as an edit
+            let front = anki_note
+                .field("Front")
+                .filter(|front| field_to_header(front) != callout.header);
+            let back = anki_note.field("Back").filter(|back| {
+                html_to_markdown(back) != html_to_markdown(&callout.content_to_html(header_lang))
+            });
+            if front.is_none() && back.is_none() {
+                checked_notes.push(anki_note);
+                continue;
+            }
+
+            match replace_callout(&content, &callout.markdown_id, front, back, header_lang) {
+                Some(new_content) => {
+                    content = new_content;
+                    changed = true;
+                    num_pulled += 1;
+                    checked_notes.push(anki_note);
+                    println!("  ^{} ({})", &callout.markdown_id, source_file);
+                }
+                None => warn!(
+                    "Cannot find callout '^{}' in '{}'.",
+                    &callout.markdown_id, source_file
+                ),
+            }
+        }
+
+        if changed && !args.dry_run {
+            write(&deck.source_file, content)?;
+        }
+    }
+
+    if args.dry_run {
+        println!("\n{} callouts would be updated.", num_pulled);
+    } else {
+        // The notes now match the markdown files, so they are no longer edits to pull or conflicts
+        for note in checked_notes {
+            if let Some(note_state) = state.notes.get_mut(&note.markdown_id) {
+                note_state.mtime = note.mtime;
+            }
+        }
+        state.save(input_dir)?;
+        println!("\nUpdated {} callouts.", num_pulled);
+    }
+
+    step.print_step(Some("Done"), Some(SPARKLE));
+    Ok(())
+}
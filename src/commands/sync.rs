@@ -1,25 +1,34 @@
-use crate::anki_connect::anki_connect_client::params::Action;
-use crate::anki_connect::anki_connect_client::response::BasicResponse;
+use super::sync_plan::{PlannedMove, SyncPlan};
+use super::{anki_connect_client, load_anki_profile};
+use crate::anki_connect::batch::{BatchOptions, DEFAULT_BATCH_SIZE, DEFAULT_RETRIES};
 use crate::anki_connect::card::CardId;
 use crate::anki_connect::decks_client::params::ChangeDeck;
-use crate::anki_connect::notes_client::params::{AddNote, UpdateNoteFields};
+use crate::anki_connect::multi_builder::{MultiBuilder, MultiHandle, MultiResults};
+use crate::anki_connect::notes_client::params::{AddNote, UpdateNoteFields, UpdateNoteModel};
 use crate::anki_connect::notes_client::responses::NoteInfo;
 use crate::anki_connect::{AnkiConnectClient, error::APIError, model::Model, note::NoteId};
 use crate::callout::Callout;
-use crate::cli::SyncArgs;
+use crate::cli::{PlanFormat, SyncArgs};
+use crate::config::{
+    CONFIG_FILE_NAME, Config, ConfigError, DEFAULT_MAX_DELETE_FRACTION, OrphanPolicy, Profile,
+};
 use crate::deck::Deck;
 use crate::find_markdown_files::find_markdown_files;
+use crate::journal::{Journal, NoteSnapshot};
 use crate::model::InternalModelMethods;
-use crate::model::ModelType;
+use crate::model::{CardDirection, CardTemplate, ModelOptions, ModelType, TtsOptions};
 use crate::note_operation::NoteOperation;
+use crate::state::{ConflictPolicy, NoteState, StableHasher, SyncState, stable_hash};
+use crate::style::build_css;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
-use std::cmp::min;
-use std::collections::HashMap;
+use serde::{Serialize, de::DeserializeOwned};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::Display;
-use std::fs::{File, read_to_string};
+use std::fs::{File, read_to_string, remove_file};
+use std::hash::{Hash, Hasher};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::thread;
 use tracing::{debug, error, info, warn};
@@ -31,16 +40,46 @@ use crate::progress::{
     BAR_CHART, CROSS, LOOKING_GLASS, PLUS, REPEAT, SHUFFLE, SPARKLE, SYNC, Step,
 };
 
+/// AnkiConnect actions used by a sync, checked before anything is changed in Anki.
+const SYNC_ACTIONS: &[&str] = &[
+    "multi",
+    "deckNames",
+    "deckNamesAndIds",
+    "createDeck",
+    "changeDeck",
+    "getDecks",
+    "deleteDecks",
+    "modelNames",
+    "findModelsByName",
+    "createModel",
+    "updateModelStyling",
+    "updateModelTemplates",
+    "modelFieldAdd",
+    "modelTemplateAdd",
+    "modelTemplateRemove",
+    "findNotes",
+    "findCards",
+    "notesInfo",
+    "addNote",
+    "updateNote",
+    "deleteNotes",
+    "suspend",
+];
+
 #[derive(Debug)]
 struct SyncStats {
     num_added: u64,
     num_added_errors: u64,
+    num_unchanged: u64,
+    num_conflicts: u64,
+    num_converted: u64,
+    num_converted_errors: u64,
     num_updated: u64,
     num_updated_errors: u64,
     num_moved: u64,
     num_moved_errors: u64,
-    num_deleted: u64,
-    num_deleted_errors: u64,
+    num_removed: u64,
+    num_removed_errors: u64,
 }
 
 impl Display for SyncStats {
@@ -49,7 +88,10 @@ impl Display for SyncStats {
             self.num_added,
             self.num_updated,
             self.num_moved,
-            self.num_deleted,
+            self.num_removed,
+            self.num_unchanged,
+            self.num_conflicts,
+            self.num_converted,
         ]
         .iter()
         .max()
@@ -57,9 +99,10 @@ impl Display for SyncStats {
         let width = (max_value + 10).to_string().len();
         let max_value = *[
             self.num_added_errors,
+            self.num_converted_errors,
             self.num_updated_errors,
             self.num_moved_errors,
-            self.num_deleted_errors,
+            self.num_removed_errors,
         ]
         .iter()
         .max()
@@ -67,54 +110,510 @@ impl Display for SyncStats {
         let width2 = (max_value + 10).to_string().len();
         write!(
             f,
-            "{:<8}{:>width$}\n{:<8}{:>width$}\n{:<8}{:>width$}\n{:<8}{:>width$}\n{:<15}{:>width2$}\n{:<15}{:>width2$}\n{:<15}{:>width2$}\n{:<15}{:>width2$}",
+            "{:<11}{:>width$}\n{:<11}{:>width$}\n{:<11}{:>width$}\n{:<11}{:>width$}\n{:<11}{:>width$}\n{:<11}{:>width$}\n{:<11}{:>width$}\n{:<17}{:>width2$}\n{:<17}{:>width2$}\n{:<17}{:>width2$}\n{:<17}{:>width2$}\n{:<17}{:>width2$}",
             "Added:",
             self.num_added,
+            "Converted:",
+            self.num_converted,
             "Updated:",
             self.num_updated,
             "Moved:",
             self.num_moved,
-            "Deleted:",
-            self.num_deleted,
+            "Removed:",
+            self.num_removed,
+            "Unchanged:",
+            self.num_unchanged,
+            "Conflicts:",
+            self.num_conflicts,
             "Added Errors:",
             self.num_added_errors,
+            "Converted Errors:",
+            self.num_converted_errors,
             "Updated Errors:",
             self.num_updated_errors,
             "Moved Errors:",
             self.num_moved_errors,
-            "Deleted Errors:",
-            self.num_deleted_errors,
+            "Removed Errors:",
+            self.num_removed_errors,
             width = width,
             width2 = width2,
         )
     }
 }
 
+/// Actions are kept together with the note they were created from, to report the failed notes.
 #[derive(Debug)]
 struct OperationParams<'a> {
-    add: Vec<AddNote<'a>>,
-    update: Vec<UpdateNoteFields<'a>>,
-    move_: Vec<ChangeDeck<'a>>,
-    delete: Vec<&'a NoteId>,
+    add: Vec<(&'a ModelType<'a>, AddNote<'a>)>,
+    change_model: Vec<(&'a ModelType<'a>, UpdateNoteModel<'a>)>,
+    update: Vec<(&'a ModelType<'a>, UpdateNoteFields<'a>)>,
+    move_: Vec<(&'a ModelType<'a>, ChangeDeck<'a>)>,
+    /// Notes whose callout no longer exists, removed according to the orphan policy.
+    delete: Vec<&'a NoteInfo>,
     notes: Vec<ModelType<'a>>,
     notes_errors: Vec<(M2AnkiError, &'a ModelType<'a>)>,
+    /// Notes that were edited in Anki since the last sync.
+    conflicts: Vec<&'a ModelType<'a>>,
+    /// Notes that use another model in Anki, together with the name of that model.
+    model_mismatches: Vec<(&'a ModelType<'a>, &'a str)>,
+}
+
+/// Note that could not be synced, as written to `failed_notes.json`.
+#[derive(Debug, Serialize)]
+struct FailedNote<'a> {
+    markdown_id: &'a str,
+    deck: &'a str,
+    operation: &'static str,
+    error: String,
+}
+
+/// Notes paired with the result or the error of their action.
+type NoteResults<'a, T> = Vec<(&'a ModelType<'a>, T)>;
+
+/// Adds an action for each note to the `multi` request, and returns the notes paired with the
+/// handle of their action.
+fn push_actions<'a, P, R>(
+    builder: &mut MultiBuilder<'a>,
+    action: &'a str,
+    items: &'a [(&'a ModelType<'a>, P)],
+) -> Result<Vec<(&'a ModelType<'a>, MultiHandle<R>)>, APIError>
+where
+    P: Serialize,
+    R: DeserializeOwned,
+{
+    items
+        .iter()
+        .map(|(note, params)| Ok((*note, builder.push(action, params)?)))
+        .collect()
+}
+
+/// Returns the notes whose action succeeded with its result, and the notes whose action failed
+/// with the error returned by AnkiConnect.
+fn note_results<'a, R>(
+    results: &MultiResults,
+    handles: Vec<(&'a ModelType<'a>, MultiHandle<R>)>,
+) -> (NoteResults<'a, R>, NoteResults<'a, String>)
+where
+    R: DeserializeOwned,
+{
+    let mut succeeded = Vec::new();
+    let mut failed = Vec::new();
+    for (note, handle) in handles {
+        match results.get(handle) {
+            Ok(result) => succeeded.push((note, result)),
+            Err(err) => failed.push((note, err.to_string())),
+        }
+    }
+    (succeeded, failed)
+}
+
+/// Fails when removing `count` of the `total` notes of the deck would exceed the allowed fraction,
+/// in a dry run only a warning is printed.
+fn check_deletions(
+    count: usize,
+    total: usize,
+    max_fraction: f64,
+    dry_run: bool,
+) -> Result<(), M2AnkiError> {
+    if count == 0 || count as f64 <= total as f64 * max_fraction {
+        return Ok(());
+    }
+    let err = M2AnkiError::TooManyDeletions {
+        count,
+        total,
+        max_fraction,
+    };
+    if dry_run {
+        warn!("{}", err);
+        Ok(())
+    } else {
+        Err(err)
+    }
+}
+
+/// Finds the decks under the parent deck that contain no cards, ignoring the given cards, and no
+/// deck with a markdown source. Only the top-most empty decks are returned, since deleting a deck
+/// also deletes its subdecks.
+fn find_empty_decks(
+    client: &AnkiConnectClient,
+    parent_deck: &str,
+    source_decks: &HashSet<&str>,
+    ignored_cards: &HashSet<&CardId>,
+) -> Result<Vec<String>, M2AnkiError> {
+    let prefix = format!("{}::", parent_deck);
+    let subdecks: Vec<String> = client
+        .decks()
+        .deck_names()?
+        .into_iter()
+        .filter(|name| name.starts_with(&prefix))
+        .collect();
+    if subdecks.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let cards: Vec<CardId> = client
+        .cards()
+        .find_cards_ids_by_deck_name(parent_deck)?
+        .into_iter()
+        .filter(|card| !ignored_cards.contains(card))
+        .collect();
+    let card_refs: Vec<&CardId> = cards.iter().collect();
+    let decks_with_cards = if card_refs.is_empty() {
+        HashMap::new()
+    } else {
+        client.decks().get_decks(&card_refs)?
+    };
+
+    // A deck is kept when it, or one of its subdecks, has cards or a markdown source
+    let mut kept_decks: HashSet<&str> = HashSet::new();
+    for name in decks_with_cards
+        .keys()
+        .map(|name| name.as_str())
+        .chain(source_decks.iter().copied())
+    {
+        let mut end = name.len();
+        loop {
+            kept_decks.insert(&name[..end]);
+            match name[..end].rfind("::") {
+                Some(index) => end = index,
+                None => break,
+            }
+        }
+    }
+
+    let empty_decks: Vec<&String> = subdecks
+        .iter()
+        .filter(|name| !kept_decks.contains(name.as_str()))
+        .collect();
+    Ok(empty_decks
+        .iter()
+        .filter(|name| {
+            !empty_decks.iter().any(|parent| {
+                name.len() > parent.len()
+                    && name.starts_with(parent.as_str())
+                    && name[parent.len()..].starts_with("::")
+            })
+        })
+        .map(|name| name.to_string())
+        .collect())
 }
 
+/// Combines the TTS settings from the command line with the ones from the config file.
+fn tts_options(args: &SyncArgs, config: &Config) -> Option<TtsOptions> {
+    let has_cli_options = args.tts_lang.is_some()
+        || !args.tts_voices.is_empty()
+        || !args.tts_fields.is_empty()
+        || args.tts_speed.is_some();
+    if args.no_tts || (config.tts.is_none() && !has_cli_options) {
+        return None;
+    }
+
+    let mut tts = config.tts.clone().unwrap_or_default();
+    if let Some(lang) = &args.tts_lang {
+        tts.lang = lang.clone();
+    }
+    if !args.tts_voices.is_empty() {
+        tts.voices = args.tts_voices.clone();
+    }
+    if !args.tts_fields.is_empty() {
+        tts.fields = args.tts_fields.clone();
+    }
+    if args.tts_speed.is_some() {
+        tts.speed = args.tts_speed;
+    }
+    Some(tts)
+}
+
+/// Change made to an existing model so that it matches the model type and options.
+#[derive(Debug)]
+enum ModelChange {
+    AddField(&'static str),
+    AddTemplate(CardTemplate),
+    RemoveTemplate(String),
+}
+
+impl Display for ModelChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelChange::AddField(field) => write!(f, "Add field '{}'", field),
+            ModelChange::AddTemplate(template) => {
+                write!(f, "Add template '{}'", template.direction)
+            }
+            ModelChange::RemoveTemplate(name) => {
+                write!(f, "Remove template '{}' and its cards", name)
+            }
+        }
+    }
+}
+
+/// Lists the fields and card templates missing from an existing model, and the templates of
+/// directions that are no longer enabled.
+fn plan_model_changes(
+    model: &Model,
+    model_type: &ModelType,
+    model_options: &ModelOptions,
+) -> Vec<ModelChange> {
+    let existing_fields = model.field_names();
+    let existing_templates = model.template_names();
+    let mut changes: Vec<ModelChange> = model_type
+        .get_field_names()
+        .iter()
+        .filter(|field| !existing_fields.contains(field))
+        .map(|field| ModelChange::AddField(field))
+        .collect();
+
+    // Templates are added before removing others, since Anki refuses to remove the last template
+    changes.extend(
+        model_type
+            .get_card_templates(model_options)
+            .into_iter()
+            .filter(|template| {
+                !existing_templates.contains(&template.direction.to_string().as_str())
+            })
+            .map(ModelChange::AddTemplate),
+    );
+    changes.extend(
+        CardDirection::all()
+            .into_iter()
+            .filter(|direction| !model_options.directions.contains(direction))
+            .map(|direction| direction.to_string())
+            .filter(|name| existing_templates.contains(&name.as_str()))
+            .map(ModelChange::RemoveTemplate),
+    );
+    changes
+}
+
+/// Applies the changes planned for an existing model and updates the remaining templates.
+fn reconcile_model(
+    client: &AnkiConnectClient,
+    model: &Model,
+    model_type: &ModelType,
+    model_options: &ModelOptions,
+    changes: Vec<ModelChange>,
+) -> Result<(), M2AnkiError> {
+    for change in changes {
+        match &change {
+            ModelChange::AddField(field) => {
+                client.models().model_field_add(&model.name, field, None)?;
+            }
+            ModelChange::AddTemplate(template) => {
+                client.models().model_template_add(
+                    &model.name,
+                    &template.direction.to_string(),
+                    &template.front,
+                    &template.back,
+                )?;
+            }
+            ModelChange::RemoveTemplate(name) => {
+                warn!(
+                    "Removing template '{}' from model '{}', its cards will be deleted.",
+                    name, &model.name
+                );
+                client.models().model_template_remove(&model.name, name)?;
+            }
+        }
+        info!("{} in model '{}'.", change, &model.name);
+    }
+
+    client
+        .models()
+        .update_model_templates(model_type.to_update_templates(&model.name, model_options))?;
+    info!("Updated model templates.");
+    Ok(())
+}
+
+/// Hash of a callout together with everything that affects the note generated from it.
+fn note_hash(settings_hash: &str, deck: &Deck, callout: &Callout) -> String {
+    let mut hasher = StableHasher::default();
+    settings_hash.hash(&mut hasher);
+    deck.qualified_name.hash(&mut hasher);
+    format!("{:?}", deck.front_matter).hash(&mut hasher);
+    callout.hash_content(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Syncs the input directory, or the profiles of the config file one after the other using the
+/// same client.
 pub fn sync(args: SyncArgs) -> Result<(), M2AnkiError> {
+    if args.profile.is_none() && !args.all_profiles {
+        let input_dir = args.input_dir.clone().unwrap_or_default();
+        let config = Config::find(args.config_file.as_deref(), &input_dir)?;
+        let client = anki_connect_client(&args.anki_connect, &config)?;
+        let (anki_sync, dry_run) = (args.anki_sync, args.dry_run);
+        sync_input(&client, args, input_dir, &config)?;
+        return sync_with_ankiweb(
+            &client,
+            anki_sync || config.anki_sync == Some(true),
+            dry_run,
+        );
+    }
+
+    let config_path = args.config_file.clone().unwrap_or_else(|| {
+        args.input_dir
+            .clone()
+            .unwrap_or_default()
+            .join(CONFIG_FILE_NAME)
+    });
+    let config = Config::load(&config_path)?;
+    let client = anki_connect_client(&args.anki_connect, &config)?;
+    let config_dir = config_path.parent().unwrap_or(Path::new(""));
+    let profiles: Vec<(&String, &Profile)> = match &args.profile {
+        Some(name) => vec![
+            config
+                .profiles
+                .get_key_value(name)
+                .ok_or_else(|| ConfigError::UnknownProfile(name.clone()))?,
+        ],
+        None => config.profiles.iter().collect(),
+    };
+    if profiles.is_empty() {
+        return Err(ConfigError::Invalid(format!(
+            "no profiles are defined in '{}'",
+            config_path.to_str().unwrap_or_default()
+        ))
+        .into());
+    }
+
+    let mut failed_profiles = Vec::new();
+    for (name, profile) in profiles {
+        info!("Syncing profile '{}'", name);
+        // Values given on the command line still take precedence over the profile
+        let mut profile_args = args.clone();
+        profile_args.deck = args.deck.clone().or(profile.deck.clone());
+        profile_args.model_type_name = args.model_type_name.clone().or(profile.model.clone());
+        profile_args.model_name = args.model_name.clone().or(profile.model_name.clone());
+        profile_args.css_file = args
+            .css_file
+            .clone()
+            .or(profile.css.as_ref().map(|css| config_dir.join(css)));
+        profile_args.header_lang = args.header_lang.clone().or(profile.lang.clone());
+
+        let mut profile_config = config.clone();
+        profile_config.directions = profile.directions.clone().or(config.directions.clone());
+        profile_config.tts = profile.tts.clone().or(config.tts.clone());
+        profile_config
+            .exclude
+            .extend(profile.exclude.iter().cloned());
+
+        let input_dir = config_dir.join(&profile.input);
+        if let Err(err) = sync_input(&client, profile_args, input_dir, &profile_config) {
+            error!("Failed to sync profile '{}': {}", name, err);
+            failed_profiles.push(name.clone());
+        }
+    }
+    if !failed_profiles.is_empty() {
+        return Err(M2AnkiError::FailedProfiles(failed_profiles));
+    }
+    sync_with_ankiweb(
+        &client,
+        args.anki_sync || config.anki_sync == Some(true),
+        args.dry_run,
+    )
+}
+
+/// Syncs the collection with AnkiWeb when it was asked for, once every change was made.
+fn sync_with_ankiweb(
+    client: &AnkiConnectClient,
+    anki_sync: bool,
+    dry_run: bool,
+) -> Result<(), M2AnkiError> {
+    if !anki_sync || dry_run {
+        return Ok(());
+    }
+    client.require_actions(&["sync"])?;
+    info!("Syncing the collection with AnkiWeb.");
+    client.sync()?;
+    Ok(())
+}
+
+fn sync_input(
+    client: &AnkiConnectClient,
+    args: SyncArgs,
+    input_dir: PathBuf,
+    config: &Config,
+) -> Result<(), M2AnkiError> {
+    let model_options = ModelOptions {
+        tts: tts_options(&args, config),
+        directions: if args.directions.is_empty() {
+            config.directions.clone().unwrap_or_else(CardDirection::all)
+        } else {
+            args.directions.clone()
+        },
+    };
+    if model_options.directions.is_empty() {
+        return Err(
+            ConfigError::Invalid("at least one card direction is required".to_string()).into(),
+        );
+    }
+    let conflict_policy = args.on_conflict.or(config.on_conflict).unwrap_or_default();
+    let prune = args.prune || config.prune.unwrap_or(false);
+    let orphan_policy = args.orphans.or(config.orphans).unwrap_or_default();
+    let max_delete_fraction = args
+        .max_delete_fraction
+        .or(config.max_delete_fraction)
+        .unwrap_or(DEFAULT_MAX_DELETE_FRACTION);
+    let batch_options = BatchOptions {
+        batch_size: args
+            .batch_size
+            .or(config.batch_size)
+            .unwrap_or(DEFAULT_BATCH_SIZE),
+        retries: args.retries.or(config.retries).unwrap_or(DEFAULT_RETRIES),
+        ..Default::default()
+    };
+    if batch_options.batch_size == 0 {
+        return Err(ConfigError::Invalid("batch-size must be at least 1".to_string()).into());
+    }
+    if !(0.0..=1.0).contains(&max_delete_fraction) {
+        return Err(ConfigError::Invalid(format!(
+            "max-delete-fraction must be between 0 and 1, got {}",
+            max_delete_fraction
+        ))
+        .into());
+    }
+    let mut state = SyncState::load(&input_dir)?;
+
     // Extract args into variables
-    let parent_deck = args.deck.unwrap().to_string();
-    let model_type_name = args.model_type_name.unwrap().to_string();
+    let parent_deck = args.deck.clone().ok_or_else(|| {
+        ConfigError::Invalid("a deck is required, pass --deck or use a profile".to_string())
+    })?;
+    let model_type_name = args
+        .model_type_name
+        .clone()
+        .unwrap_or_else(|| "Basic".to_string());
     let model_name = args
         .model_name
         .unwrap_or_else(|| format!("md2anki {}", &model_type_name));
-    let header_lang: Option<String> = Some(args.header_lang.clone().unwrap().to_string());
-    let input_dir = &args.input_dir;
+    let header_lang: Option<String> =
+        Some(args.header_lang.clone().unwrap_or_else(|| "en".to_string()));
+    let input_dir = &input_dir;
+    // The trash deck is outside of the parent deck by default, so that trashed notes are not
+    // considered as orphans again by the next sync
+    let trash_deck = args
+        .trash_deck
+        .clone()
+        .or(config.trash_deck.clone())
+        .unwrap_or_else(|| format!("{} Trash", &parent_deck));
 
-    let mut step = Step::new(1, 10);
-    step.print_step(Some("Connecting to Anki"), Some(LOOKING_GLASS));
+    let mut journal = Journal::new(&parent_deck);
+
+    // Files and notes that didn't change since the last sync are skipped, unless the settings
+    // that affect the generated notes changed
+    let settings_hash = stable_hash(&(
+        &parent_deck,
+        &model_type_name,
+        &model_name,
+        &header_lang,
+        format!("{:?}", &model_options),
+    ));
+    let is_incremental = !args.full && !args.delete_existing && state.settings == settings_hash;
+    let previous_file_hashes = if is_incremental {
+        state.files.clone()
+    } else {
+        HashMap::new()
+    };
 
-    // Create a client with default connection (localhost:8765)
-    let client = AnkiConnectClient::new(None, None);
+    let mut step = Step::new(1, 11);
+    step.print_step(Some("Connecting to Anki"), Some(LOOKING_GLASS));
 
     // Prepare channel for async initial processing
     let (tx, rx) = std::sync::mpsc::channel();
@@ -127,22 +626,57 @@ pub fn sync(args: SyncArgs) -> Result<(), M2AnkiError> {
         let _ = tx_client.send(("client", res));
     });
 
-    let input_dir_clone = args.input_dir.clone();
+    let input_dir_clone = input_dir.clone();
+    let excluded: Vec<PathBuf> = config
+        .exclude
+        .iter()
+        .chain(&args.exclude)
+        .map(|path| input_dir.join(path))
+        .collect();
     let parent_deck_clone = parent_deck.clone();
     let tx_files = tx.clone();
     step.print_step(Some("Extracting decks"), Some(LOOKING_GLASS));
     let markdown_files_hadle = thread::spawn(move || {
-        let markdown_files = find_markdown_files(&input_dir_clone).unwrap_or_else(|_| Vec::new());
+        let markdown_files: Vec<PathBuf> = find_markdown_files(&input_dir_clone)
+            .unwrap_or_else(|_| Vec::new())
+            .into_iter()
+            .filter(|path| !excluded.iter().any(|excluded| path.starts_with(excluded)))
+            .collect();
         let found_files = !markdown_files.is_empty();
         let _ = tx_files.send(("md_files", found_files));
 
         info!("Found {} markdown files", &markdown_files.len());
 
-        let decks: Vec<Deck> = markdown_files
+        let files: Vec<(PathBuf, String, String)> = markdown_files
+            .into_par_iter()
+            .filter_map(|path| {
+                let content = read_to_string(&path).ok()?;
+                let hash = stable_hash(&content);
+                Some((path, content, hash))
+            })
+            .collect();
+        let file_hashes: HashMap<PathBuf, String> = files
+            .iter()
+            .map(|(path, _, hash)| (path.clone(), hash.clone()))
+            .collect();
+        let (unchanged_files, changed_files): (Vec<_>, Vec<_>) =
+            files.into_par_iter().partition(|(path, _, hash)| {
+                previous_file_hashes
+                    .get(path)
+                    .is_some_and(|previous| previous == hash)
+            });
+        let unchanged_files: HashSet<PathBuf> = unchanged_files
+            .into_iter()
+            .map(|(path, _, _)| path)
+            .collect();
+        info!(
+            "Skipping {} unchanged markdown files",
+            unchanged_files.len()
+        );
+
+        let decks: Vec<Deck> = changed_files
             .par_iter()
-            .map(Deck::try_from)
-            .filter(Result::is_ok)
-            .map(Result::unwrap)
+            .map(|(path, content, _)| Deck::from_content(path, content))
             .filter(|deck| !deck.callouts.is_empty())
             .map(|mut deck| {
                 deck.qualified_name = deck
@@ -153,7 +687,10 @@ pub fn sync(args: SyncArgs) -> Result<(), M2AnkiError> {
             .collect();
 
         let total_callouts: usize = decks.par_iter().map(|deck| deck.callouts.len()).sum();
-        let _ = tx_files.send(("num_callouts", total_callouts > 0));
+        let _ = tx_files.send((
+            "num_callouts",
+            total_callouts > 0 || !unchanged_files.is_empty(),
+        ));
 
         let num_found_decks: usize = decks.len();
         let num_total_callouts: usize = decks.par_iter().map(|d| d.callouts.len()).sum();
@@ -188,15 +725,28 @@ pub fn sync(args: SyncArgs) -> Result<(), M2AnkiError> {
 
         let model_type = ModelType::from_str(&model_type_name);
 
-        // Load css file if it exists
-        let css_file = args.css_file.clone().unwrap_or_default();
-        let css = if css_file.is_file() {
-            read_to_string(css_file)
-        } else {
-            Ok("".to_string())
-        };
+        // Load css file if it exists and layer it on top of the default stylesheet
+        let css = match &args.css_file {
+            Some(css_file) if css_file.is_file() => read_to_string(css_file).map(Some),
+            Some(css_file) => {
+                warn!(
+                    "CSS file not found: '{}'",
+                    css_file.to_str().unwrap_or_default()
+                );
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+        .map(|user_css| build_css(user_css.as_deref(), !args.no_default_css));
 
-        (decks, total_callouts, model_type, css)
+        (
+            decks,
+            total_callouts,
+            model_type,
+            css,
+            file_hashes,
+            unchanged_files,
+        )
     });
 
     for _ in 0..3 {
@@ -228,204 +778,615 @@ pub fn sync(args: SyncArgs) -> Result<(), M2AnkiError> {
     client_handle
         .join()
         .map_err(|err| M2AnkiError::ThreadPanic(err))?;
-    let (mut decks, total_callouts, model_type, css) = markdown_files_hadle
-        .join()
-        .map_err(|err| M2AnkiError::ThreadPanic(err))?;
+    client.require_actions(SYNC_ACTIONS)?;
+    if args.change_model {
+        client.require_actions(&["updateNoteModel"])?;
+    }
+    let anki_profile = load_anki_profile(client, &args.anki_connect, config, args.dry_run)?;
+    let (mut decks, total_callouts, model_type, css, file_hashes, mut unchanged_files) =
+        markdown_files_hadle
+            .join()
+            .map_err(|err| M2AnkiError::ThreadPanic(err))?;
 
     let model_type = model_type?;
     let css = css?;
 
-    let mut created_model = false;
+    if let Some(tts) = &model_options.tts {
+        tts.validate(model_type.get_field_names())
+            .map_err(ConfigError::Invalid)?;
+    }
+
+    let mut plan = SyncPlan::default();
 
-    let note_type: Model = match client.models().find_by_name(vec![&model_name]) {
-        Ok(models) => {
-            if models.is_empty() {
-                let new_model = client
-                    .models()
-                    .create_model(model_type.to_create_model(&model_name, Some(&css)))?;
-                created_model = true;
-                new_model
+    let existing_model: Option<Model> = client
+        .models()
+        .find_by_name(vec![&model_name])
+        .ok()
+        .and_then(|models| models.first().cloned());
+
+    match existing_model {
+        Some(note_type) => {
+            let model_changes = plan_model_changes(&note_type, &model_type, &model_options);
+            let removed_templates: Vec<String> = model_changes
+                .iter()
+                .filter_map(|change| match change {
+                    ModelChange::RemoveTemplate(name) => Some(name.clone()),
+                    _ => None,
+                })
+                .collect();
+            if !removed_templates.is_empty() && !args.remove_templates && !args.dry_run {
+                return Err(M2AnkiError::TemplateRemoval {
+                    model: note_type.name.clone(),
+                    templates: removed_templates,
+                });
+            }
+            if args.dry_run {
+                plan.model = model_changes.iter().map(ToString::to_string).collect();
             } else {
-                models.first().unwrap().to_owned()
+                if !css.is_empty() {
+                    client
+                        .models()
+                        .update_model_styling(&note_type.name, css.as_str())?;
+                    info!("Updated model CSS.");
+                }
+                reconcile_model(
+                    client,
+                    &note_type,
+                    &model_type,
+                    &model_options,
+                    model_changes,
+                )?;
             }
         }
-        Err(_) => {
-            let new_model = client
-                .models()
-                .create_model(model_type.to_create_model(&model_name, Some(&css)))?;
-            created_model = true;
-            new_model
+        None if args.dry_run => plan.model.push(format!("Create model '{}'", &model_name)),
+        None => {
+            client.models().create_model(model_type.to_create_model(
+                &model_name,
+                Some(&css),
+                &model_options,
+            ))?;
         }
-    };
+    }
 
-    if !css.is_empty() && !created_model {
-        let _ = client
-            .models()
-            .update_model_styling(&note_type.name, css.as_str());
-        info!("Updated model CSS.");
+    // Restrict the directions chosen in the front matter of each file to the ones of the model
+    for deck in decks.iter_mut() {
+        if let Some(directions) = deck.front_matter.directions.as_mut() {
+            directions.retain(|direction| {
+                let enabled = model_options.directions.contains(direction);
+                if !enabled {
+                    warn!(
+                        "Direction '{}' requested by '{}' is not enabled for the model.",
+                        direction,
+                        deck.source_file.to_str().unwrap_or_default()
+                    );
+                }
+                enabled
+            });
+            if directions.is_empty() {
+                warn!(
+                    "No enabled directions left for '{}', using the directions of the model.",
+                    deck.source_file.to_str().unwrap_or_default()
+                );
+                deck.front_matter.directions = None;
+            }
+        }
+        if let Some(tts) = deck.front_matter.tts(model_options.tts.as_ref())
+            && let Err(e) = tts.validate(model_type.get_field_names())
+        {
+            warn!(
+                "Ignoring the TTS settings of '{}', using the TTS of the model: {}",
+                deck.source_file.to_str().unwrap_or_default(),
+                e
+            );
+            deck.front_matter.clear_tts();
+        }
     }
 
     // Delete the deck
     if args.delete_existing {
-        let _ = client.decks().delete(&parent_deck);
+        let num_notes = if client.decks().find_deck_id_by_name(&parent_deck).is_ok() {
+            client
+                .notes()
+                .find_notes_ids_by_deck_name(&parent_deck)?
+                .len()
+        } else {
+            0
+        };
+        if args.dry_run {
+            plan.delete_deck = Some(parent_deck.clone());
+        } else {
+            if num_notes > 0 {
+                let notes = client.notes().get_notes_by_deck_name(&parent_deck)?;
+                journal.deleted = NoteSnapshot::from_notes(client, &notes)?;
+            }
+            client.decks().delete(&parent_deck)?;
+            journal.save(input_dir)?;
+        }
     }
 
     // dbg!(&decks);
     // return Ok(());
 
     // Get existing notes
-    let anki_notes_in_deck = if client.decks().find_deck_id_by_name(&parent_deck).is_ok() {
-        client.notes().get_notes_by_deck_name(&parent_deck)?
+    // The existing notes are ignored by a dry run, since the deck would have been deleted. They
+    // are kept sorted, so that the requests built from them can be replayed.
+    let anki_note_ids: BTreeSet<NoteId> = if args.delete_existing && args.dry_run {
+        BTreeSet::new()
+    } else if client.decks().find_deck_id_by_name(&parent_deck).is_ok() {
+        client
+            .notes()
+            .find_notes_ids_by_deck_name(&parent_deck)?
+            .into_iter()
+            .collect()
     } else {
+        BTreeSet::new()
+    };
+
+    // Unchanged files whose notes were deleted in Anki have to be synced again
+    let mut missing_files: Vec<PathBuf> = unchanged_files
+        .iter()
+        .filter(|path| {
+            state
+                .notes
+                .values()
+                .any(|note| note.file == **path && !anki_note_ids.contains(&note.note_id))
+        })
+        .cloned()
+        .collect();
+    missing_files.sort();
+    for path in missing_files {
+        unchanged_files.remove(&path);
+        let mut deck = Deck::from_content(&path, &read_to_string(&path)?);
+        deck.qualified_name = deck
+            .get_qualified_name(Some(input_dir), Some(&parent_deck))
+            .unwrap_or_default();
+        if !deck.callouts.is_empty() {
+            decks.push(deck);
+        }
+    }
+
+    let note_hashes: HashMap<String, String> = decks
+        .iter()
+        .flat_map(|deck| {
+            deck.callouts.iter().map(|callout| {
+                (
+                    callout.markdown_id.clone(),
+                    note_hash(&settings_hash, deck, callout),
+                )
+            })
+        })
+        .collect();
+
+    // Notes that are known to be up to date, their info doesn't need to be downloaded
+    let unchanged_note_ids: HashSet<String> = state
+        .notes
+        .iter()
+        .filter(|(markdown_id, note)| {
+            anki_note_ids.contains(&note.note_id)
+                && (unchanged_files.contains(&note.file)
+                    || (is_incremental
+                        && note_hashes
+                            .get(*markdown_id)
+                            .is_some_and(|hash| state.is_note_unchanged(markdown_id, hash))))
+        })
+        .map(|(markdown_id, _)| markdown_id.clone())
+        .collect();
+    let known_note_ids: HashSet<NoteId> = unchanged_note_ids
+        .iter()
+        .map(|markdown_id| state.notes[markdown_id].note_id)
+        .collect();
+    let num_skipped_notes = state
+        .notes
+        .values()
+        .filter(|note| {
+            unchanged_files.contains(&note.file) && known_note_ids.contains(&note.note_id)
+        })
+        .count();
+
+    let anki_note_ids_to_fetch: Vec<NoteId> = anki_note_ids
+        .iter()
+        .filter(|note_id| !known_note_ids.contains(note_id))
+        .copied()
+        .collect();
+    let anki_notes_in_deck = if anki_note_ids_to_fetch.is_empty() {
         Vec::new()
+    } else {
+        client.notes().notes_info_by_id(&anki_note_ids_to_fetch)?
     };
+    // The notes are read from the open collection, which must still be the one of the profile
+    if let Some(expected) = &anki_profile
+        && let Some(note) = anki_notes_in_deck
+            .iter()
+            .find(|note| &note.profile != expected)
+    {
+        return Err(M2AnkiError::AnkiProfileMismatch {
+            expected: expected.clone(),
+            found: note.profile.clone(),
+        });
+    }
 
     let mut operation_params = OperationParams {
         add: vec![],
+        change_model: vec![],
         update: vec![],
         move_: vec![],
         delete: vec![],
         notes: vec![],
         notes_errors: vec![],
+        conflicts: vec![],
+        model_mismatches: vec![],
     };
 
-    if !anki_notes_in_deck.is_empty() {
-        let markdown_id_to_anki_note: HashMap<&String, &NoteInfo> = anki_notes_in_deck
-            .par_iter()
-            .map(|note| (&note.markdown_id, note))
-            .collect();
+    let markdown_id_to_anki_note: HashMap<&String, &NoteInfo> = anki_notes_in_deck
+        .par_iter()
+        .map(|note| (&note.markdown_id, note))
+        .collect();
 
-        let markdown_id_to_anki_note_id: HashMap<&String, &NoteId> = anki_notes_in_deck
-            .par_iter()
-            .map(|note| (&note.markdown_id, &note.note_id))
-            .collect();
+    let markdown_id_to_anki_note_id: HashMap<&String, &NoteId> = anki_notes_in_deck
+        .par_iter()
+        .map(|note| (&note.markdown_id, &note.note_id))
+        .collect();
 
-        let anki_all_card_ids: Vec<&CardId> = anki_notes_in_deck
-            .par_iter()
-            .map(|note| &note.cards)
-            .flatten()
-            .collect();
+    let anki_all_card_ids: Vec<&CardId> = anki_notes_in_deck
+        .par_iter()
+        .map(|note| &note.cards)
+        .flatten()
+        .collect();
 
-        let anki_decks: HashMap<String, Vec<CardId>> =
-            client.decks().get_decks(&anki_all_card_ids)?;
+    let anki_decks: HashMap<String, Vec<CardId>> = if anki_all_card_ids.is_empty() {
+        HashMap::new()
+    } else {
+        client.decks().get_decks(&anki_all_card_ids)?
+    };
 
-        let anki_card_ids_to_deck: HashMap<&CardId, &str> = anki_decks
-            .par_iter()
-            .map(|(name, cards)| cards.par_iter().map(|card| (card, name.as_str())))
-            .flatten()
-            .collect();
+    let anki_card_ids_to_deck: HashMap<&CardId, &str> = anki_decks
+        .par_iter()
+        .map(|(name, cards)| cards.par_iter().map(|card| (card, name.as_str())))
+        .flatten()
+        .collect();
 
-        let markdown_id_to_anki_deck: Result<HashMap<&String, &str>, M2AnkiError> =
-            anki_notes_in_deck
-                .par_iter()
-                .map(|note| {
-                    let card_id = note.cards.first().ok_or(M2AnkiError::NoteHasNoCards)?;
-                    let deck =
-                        anki_card_ids_to_deck
-                            .get(card_id)
-                            .ok_or(M2AnkiError::DeckNameNotFound(format!(
-                                "Searching for {:?}",
-                                card_id
-                            )))?;
-                    Ok((&note.markdown_id, *deck))
-                })
-                .collect();
-        let markdown_id_to_anki_deck = markdown_id_to_anki_deck?;
-
-        // Set operations for each Callout
-        decks.par_iter_mut().for_each(|deck| {
-            let _ = deck.callouts.par_iter_mut().try_for_each(|callout| {
-                // Callout is new
-                if !markdown_id_to_anki_note_id.contains_key(&callout.markdown_id) {
-                    callout.operation = NoteOperation::Add;
-                    Ok::<(), M2AnkiError>(())
+    let markdown_id_to_anki_deck: Result<HashMap<&String, &str>, M2AnkiError> = anki_notes_in_deck
+        .par_iter()
+        .map(|note| {
+            let card_id = note.cards.first().ok_or(M2AnkiError::NoteHasNoCards)?;
+            let deck = anki_card_ids_to_deck
+                .get(card_id)
+                .ok_or(M2AnkiError::DeckNameNotFound(format!(
+                    "Searching for {:?}",
+                    card_id
+                )))?;
+            Ok((&note.markdown_id, *deck))
+        })
+        .collect();
+    let markdown_id_to_anki_deck = markdown_id_to_anki_deck?;
+
+    // Set operations for each Callout
+    decks.par_iter_mut().for_each(|deck| {
+        let _ = deck.callouts.par_iter_mut().try_for_each(|callout| {
+            // Callout didn't change since the last sync
+            if unchanged_note_ids.contains(&callout.markdown_id) {
+                callout.operation = NoteOperation::Unchanged;
+                Ok::<(), M2AnkiError>(())
+            }
+            // Callout is new
+            else if !markdown_id_to_anki_note_id.contains_key(&callout.markdown_id) {
+                callout.operation = NoteOperation::Add;
+                Ok::<(), M2AnkiError>(())
+            } else {
+                if markdown_id_to_anki_deck
+                    .get(&callout.markdown_id)
+                    .ok_or(M2AnkiError::DeckNameNotFound(format!(
+                        "Searching for {:?}",
+                        &callout.markdown_id
+                    )))?
+                    .eq(&deck.qualified_name)
+                {
+                    callout.operation = NoteOperation::Update;
                 } else {
-                    if markdown_id_to_anki_deck
-                        .get(&callout.markdown_id)
-                        .ok_or(M2AnkiError::DeckNameNotFound(format!(
-                            "Searching for {:?}",
-                            &callout.markdown_id
-                        )))?
-                        .eq(&deck.qualified_name)
-                    {
-                        callout.operation = NoteOperation::Update;
-                    } else {
-                        callout.operation = NoteOperation::Move;
-                    }
-                    Ok::<(), M2AnkiError>(())
+                    callout.operation = NoteOperation::Move;
                 }
-            });
+                Ok::<(), M2AnkiError>(())
+            }
         });
+    });
 
-        operation_params.notes = decks
-            .par_iter()
-            .map(|deck| {
-                deck.callouts.par_iter().map(|callout| {
-                    model_type.from_callout(&callout, header_lang.as_deref(), &deck.qualified_name)
-                })
+    operation_params.notes = decks
+        .par_iter()
+        .map(|deck| {
+            deck.callouts.par_iter().map(|callout| {
+                let mut note = model_type.from_callout(
+                    callout,
+                    header_lang.as_deref(),
+                    &deck.qualified_name,
+                    deck.front_matter
+                        .directions
+                        .as_deref()
+                        .unwrap_or(&model_options.directions),
+                    deck.front_matter.tts(model_options.tts.as_ref()).as_ref(),
+                );
+                if let Some(anki_note) = markdown_id_to_anki_note.get(note.get_markdown_id()) {
+                    if anki_note.model_name != model_name {
+                        note.set_operation(NoteOperation::ChangeModel);
+                    } else if matches!(note.get_operation(), NoteOperation::Update) {
+                        if anki_note.is_up_to_date(&note.get_fields()) {
+                            // Notes whose fields already match the ones in Anki don't need to be updated
+                            note.set_operation(NoteOperation::Unchanged);
+                        } else if state.is_conflicted(anki_note)
+                            && conflict_policy != ConflictPolicy::Markdown
+                        {
+                            note.set_operation(NoteOperation::Conflict);
+                        }
+                    }
+                }
+                note
             })
-            .flatten()
-            .collect();
+        })
+        .flatten()
+        .collect();
 
-        operation_params
-            .notes
-            .iter()
-            .for_each(|note| match note.get_operation() {
-                NoteOperation::Add => operation_params.add.push(AddNote::new(
-                    note.to_add_note(note.get_deck_name(), &model_name),
-                )),
-                NoteOperation::Update => {
-                    match markdown_id_to_anki_note_id.get(note.get_markdown_id()) {
-                        Some(note_id) => operation_params.update.push(note.to_update_note(note_id)),
-                        None => operation_params.notes_errors.push((
-                            M2AnkiError::NoteIdNotFound(note.get_markdown_id().to_string()),
-                            &note,
-                        )),
+    operation_params
+        .notes
+        .iter()
+        .for_each(|note| match note.get_operation() {
+            NoteOperation::Add => operation_params.add.push((
+                note,
+                AddNote::new(note.to_add_note(note.get_deck_name(), &model_name)),
+            )),
+            NoteOperation::Update => {
+                // Only reached by conflicting notes when markdown wins
+                if markdown_id_to_anki_note
+                    .get(note.get_markdown_id())
+                    .is_some_and(|anki_note| state.is_conflicted(anki_note))
+                {
+                    operation_params.conflicts.push(note);
+                }
+                match markdown_id_to_anki_note_id.get(note.get_markdown_id()) {
+                    Some(note_id) => operation_params
+                        .update
+                        .push((note, note.to_update_note(note_id))),
+                    None => operation_params.notes_errors.push((
+                        M2AnkiError::NoteIdNotFound(note.get_markdown_id().to_string()),
+                        note,
+                    )),
+                }
+            }
+            NoteOperation::Move => {
+                let anki_note = markdown_id_to_anki_note
+                    .get(&note.get_markdown_id())
+                    .unwrap();
+                let cards: Vec<&CardId> = anki_note.cards.iter().collect();
+                operation_params
+                    .move_
+                    .push((note, ChangeDeck::new(cards, note.get_deck_name())));
+                // Moved notes can be edited at the same time
+                if !anki_note.is_up_to_date(&note.get_fields()) {
+                    let is_conflicted = state.is_conflicted(anki_note);
+                    if is_conflicted {
+                        operation_params.conflicts.push(note);
+                    }
+                    if !is_conflicted || conflict_policy == ConflictPolicy::Markdown {
+                        operation_params
+                            .update
+                            .push((note, note.to_update_note(&anki_note.note_id)));
                     }
                 }
-                NoteOperation::Move => {
-                    let anki_note = markdown_id_to_anki_note
-                        .get(&note.get_markdown_id())
-                        .unwrap();
+            }
+            NoteOperation::Conflict => operation_params.conflicts.push(note),
+            NoteOperation::ChangeModel => {
+                let anki_note = markdown_id_to_anki_note
+                    .get(&note.get_markdown_id())
+                    .unwrap();
+                if !args.change_model {
+                    operation_params
+                        .model_mismatches
+                        .push((note, &anki_note.model_name));
+                    return;
+                }
+                operation_params.change_model.push((
+                    note,
+                    note.to_update_note_model(
+                        &anki_note.note_id,
+                        &model_name,
+                        anki_note.tags.iter().map(String::as_str).collect(),
+                    ),
+                ));
+                if markdown_id_to_anki_deck.get(note.get_markdown_id())
+                    != Some(&note.get_deck_name())
+                {
                     let cards: Vec<&CardId> = anki_note.cards.iter().collect();
                     operation_params
                         .move_
-                        .push(ChangeDeck::new(cards, &note.get_deck_name()));
+                        .push((note, ChangeDeck::new(cards, note.get_deck_name())));
                 }
-                _ => (),
-            });
+            }
+            _ => (),
+        });
 
-        // Check if notes need to be deleted
-        let callouts_map: HashMap<&String, &Callout> = decks
-            .par_iter()
-            .flat_map(|deck| {
-                deck.callouts
-                    .par_iter()
-                    .map(|callout| (&callout.markdown_id, callout))
-            })
-            .collect();
-        operation_params.delete = anki_notes_in_deck
-            .par_iter()
-            .filter_map(|note| {
-                if !callouts_map.contains_key(&note.markdown_id) {
-                    Some(&note.note_id)
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<&NoteId>>()
+    if !operation_params.model_mismatches.is_empty() {
+        let mut msg = vec![format!(
+            "{} notes use a different model than '{}' and were not synced, pass --change-model to convert them:",
+            operation_params.model_mismatches.len(),
+            &model_name
+        )];
+        for (note, anki_model_name) in &operation_params.model_mismatches {
+            msg.push(format!(
+                "  {} ({}): '{}'",
+                note.get_markdown_id(),
+                note.get_deck_name(),
+                anki_model_name
+            ));
+        }
+        warn!("{}", msg.join("\n"));
     }
 
+    // Check if notes need to be deleted
+    let callouts_map: HashMap<&String, &Callout> = decks
+        .par_iter()
+        .flat_map(|deck| {
+            deck.callouts
+                .par_iter()
+                .map(|callout| (&callout.markdown_id, callout))
+        })
+        .collect();
+    let orphans: Vec<&NoteInfo> = anki_notes_in_deck
+        .par_iter()
+        .filter(|note| !callouts_map.contains_key(&note.markdown_id))
+        .filter(|note| {
+            // Notes already in the trash deck are left there
+            orphan_policy != OrphanPolicy::Trash
+                || markdown_id_to_anki_deck
+                    .get(&note.markdown_id)
+                    .is_none_or(|deck| {
+                        *deck != trash_deck && !deck.starts_with(&format!("{}::", &trash_deck))
+                    })
+        })
+        .collect();
+    if prune {
+        operation_params.delete = orphans.clone();
+        check_deletions(
+            operation_params.delete.len(),
+            anki_note_ids.len(),
+            max_delete_fraction,
+            args.dry_run,
+        )?;
+    } else if !orphans.is_empty() {
+        warn!(
+            "{} notes in Anki have no callout in the markdown files, pass --prune to remove them.",
+            orphans.len()
+        );
+    }
+
+    let num_unchanged = operation_params
+        .notes
+        .iter()
+        .filter(|note| matches!(note.get_operation(), NoteOperation::Unchanged))
+        .count();
+    let num_skipped_conflicts = operation_params
+        .notes
+        .iter()
+        .filter(|note| matches!(note.get_operation(), NoteOperation::Conflict))
+        .count();
+
     debug!(
-        "OperationParams {{ add: {:?}, update: {:?}, move_: {:?}, delete: {:?} }}",
+        "OperationParams {{ add: {:?}, change_model: {:?}, update: {:?}, move_: {:?}, delete: {:?}, unchanged: {:?}, model_mismatches: {:?} }}",
         &operation_params.add.len(),
+        &operation_params.change_model.len(),
         &operation_params.update.len(),
         &operation_params.move_.len(),
-        &operation_params.delete.len()
+        &operation_params.delete.len(),
+        num_unchanged,
+        &operation_params.model_mismatches.len()
     );
 
     // dbg!(&operation_params);
 
+    // Decks that still have a markdown source are never deleted, even when they are empty
+    let source_decks: HashSet<&str> = decks
+        .iter()
+        .map(|deck| deck.qualified_name.as_str())
+        .chain(
+            state
+                .notes
+                .values()
+                .filter(|note| unchanged_files.contains(&note.file))
+                .map(|note| note.deck.as_str()),
+        )
+        .chain([trash_deck.as_str()])
+        .collect();
+
+    let conflicted_ids: HashSet<&String> = operation_params
+        .conflicts
+        .iter()
+        .map(|note| note.get_markdown_id())
+        .collect();
+
+    if args.dry_run {
+        for note in &operation_params.notes {
+            let markdown_id = note.get_markdown_id();
+            let anki_note = markdown_id_to_anki_note.get(markdown_id);
+            let anki_deck = markdown_id_to_anki_deck.get(markdown_id);
+            let deck_plan = plan.deck(note.get_deck_name());
+            match note.get_operation() {
+                NoteOperation::Add => deck_plan.add.push(markdown_id.clone()),
+                NoteOperation::Update => deck_plan.update.push(markdown_id.clone()),
+                NoteOperation::Unchanged => deck_plan.unchanged += 1,
+                NoteOperation::Move => {
+                    let is_skipped = conflicted_ids.contains(markdown_id)
+                        && conflict_policy != ConflictPolicy::Markdown;
+                    if !is_skipped
+                        && anki_note
+                            .is_some_and(|anki_note| !anki_note.is_up_to_date(&note.get_fields()))
+                    {
+                        deck_plan.update.push(markdown_id.clone());
+                    }
+                    deck_plan.move_.push(PlannedMove {
+                        markdown_id: markdown_id.clone(),
+                        from: anki_deck.unwrap_or(&"").to_string(),
+                    });
+                }
+                NoteOperation::ChangeModel if args.change_model => {
+                    deck_plan.change_model.push(markdown_id.clone());
+                    if anki_deck != Some(&note.get_deck_name()) {
+                        deck_plan.move_.push(PlannedMove {
+                            markdown_id: markdown_id.clone(),
+                            from: anki_deck.unwrap_or(&"").to_string(),
+                        });
+                    }
+                }
+                _ => (),
+            }
+        }
+        for note in &operation_params.conflicts {
+            plan.deck(note.get_deck_name())
+                .conflict
+                .push(note.get_markdown_id().clone());
+        }
+        if prune {
+            plan.orphans = Some(orphan_policy);
+            if orphan_policy == OrphanPolicy::Trash {
+                plan.trash_deck = Some(trash_deck.clone());
+            }
+        }
+        for note in &orphans {
+            let deck_name = markdown_id_to_anki_deck
+                .get(&note.markdown_id)
+                .unwrap_or(&parent_deck.as_str())
+                .to_string();
+            let deck_plan = plan.deck(&deck_name);
+            if prune {
+                deck_plan.delete.push(note.markdown_id.clone());
+            } else {
+                deck_plan.orphaned.push(note.markdown_id.clone());
+            }
+        }
+
+        if prune && !args.delete_existing {
+            // The cards that would be moved out of their deck or deleted are left out, to find
+            // the decks that would become empty
+            let ignored_cards: HashSet<&CardId> = operation_params
+                .notes
+                .iter()
+                .filter(|note| matches!(note.get_operation(), NoteOperation::Move))
+                .filter_map(|note| markdown_id_to_anki_note.get(note.get_markdown_id()))
+                .chain(
+                    operation_params
+                        .delete
+                        .iter()
+                        .filter(|_| orphan_policy != OrphanPolicy::Suspend),
+                )
+                .flat_map(|note| &note.cards)
+                .collect();
+            plan.delete_decks =
+                find_empty_decks(client, &parent_deck, &source_decks, &ignored_cards)?;
+        }
+
+        match args.plan_format {
+            PlanFormat::Table => println!("{}", plan),
+            PlanFormat::Json => println!("{}", serde_json::to_string_pretty(&plan)?),
+        }
+        return Ok(());
+    }
+
     // Prepare progress bars
     let m = MultiProgress::new();
     let sty = ProgressStyle::with_template(
@@ -440,6 +1401,9 @@ pub fn sync(args: SyncArgs) -> Result<(), M2AnkiError> {
     ));
     global_pbar.set_style(sty.clone());
     global_pbar.set_message("Overall");
+    global_pbar.inc(
+        (num_unchanged + num_skipped_conflicts + operation_params.model_mismatches.len()) as u64,
+    );
 
     // let decks_pbar = m.add(ProgressBar::new(
     //     decks
@@ -451,172 +1415,365 @@ pub fn sync(args: SyncArgs) -> Result<(), M2AnkiError> {
     // decks_pbar.set_message("Decks");
 
     // Prepare stats and error tracking
-    let mut failed_notes: Vec<(PathBuf, Vec<(String, ModelType)>)> = Vec::new();
+    let mut failed_notes: Vec<(&ModelType, &'static str, String)> = operation_params
+        .notes_errors
+        .iter()
+        .map(|(err, note)| (*note, "update", err.to_string()))
+        .collect();
     let mut sync_stats = SyncStats {
         num_added: 0,
         num_added_errors: 0,
+        num_unchanged: (num_unchanged + num_skipped_notes) as u64,
+        num_conflicts: operation_params.conflicts.len() as u64,
+        num_converted: 0,
+        num_converted_errors: 0,
         num_updated: 0,
-        num_updated_errors: 0,
+        num_updated_errors: operation_params.notes_errors.len() as u64,
         num_moved: 0,
         num_moved_errors: 0,
-        num_deleted: 0,
-        num_deleted_errors: 0,
+        num_removed: 0,
+        num_removed_errors: 0,
+    };
+
+    // Notes changed by the sync are journaled as they were before, so that `undo` can revert them
+    let snapshot = |note: &ModelType| {
+        markdown_id_to_anki_note
+            .get(note.get_markdown_id())
+            .map(|anki_note| {
+                let deck = markdown_id_to_anki_deck
+                    .get(&anki_note.markdown_id)
+                    .copied()
+                    .unwrap_or_default();
+                NoteSnapshot::new(anki_note, deck)
+            })
     };
 
     // Start main upload loop
-    // Add new notes
-    m.suspend(|| {
-        step.print_step(Some("Syncing notes to Anki"), Some(SYNC));
-        step.print_step(Some("Adding new notes"), Some(PLUS));
-    });
+    m.suspend(|| step.print_step(Some("Syncing notes to Anki"), Some(SYNC)));
     if !operation_params.add.is_empty() {
-        let action = "addNote";
-        let add_actions: Vec<Action<AddNote>> = operation_params
+        // AnkiConnect only adds notes to decks that already exist
+        let existing_decks: HashSet<String> = client.decks().deck_names()?.into_iter().collect();
+        let new_decks: BTreeSet<&str> = operation_params
             .add
-            .par_iter()
-            .map(|add_note| Action::new(action, 6, add_note))
+            .iter()
+            .map(|(note, _)| note.get_deck_name())
+            .filter(|deck| !existing_decks.contains(*deck))
             .collect();
+        for deck in new_decks {
+            client.decks().create_deck(deck)?;
+        }
+    }
 
-        let add_actions_refs: Vec<&Action<AddNote>> = add_actions.par_iter().collect();
+    // New, converted, updated and moved notes are sent in the same requests, AnkiConnect applies
+    // the actions in order so notes are converted to the synced model before they are updated
+    let mut builder = client.multi_builder();
+    let add_handles = push_actions::<_, NoteId>(&mut builder, "addNote", &operation_params.add)?;
+    let change_model_handles = push_actions::<_, ()>(
+        &mut builder,
+        "updateNoteModel",
+        &operation_params.change_model,
+    )?;
+    let update_handles =
+        push_actions::<_, ()>(&mut builder, "updateNote", &operation_params.update)?;
+    let move_handles = push_actions::<_, ()>(&mut builder, "changeDeck", &operation_params.move_)?;
+    let results = if builder.is_empty() {
+        None
+    } else {
+        Some(builder.send_batched(&batch_options, &|num_actions| {
+            global_pbar.inc(num_actions as u64)
+        }))
+    };
 
-        let chunk_size = min(add_actions_refs.len(), 500);
+    // Add new notes
+    m.suspend(|| step.print_step(Some("Adding new notes"), Some(PLUS)));
+    match &results {
+        Some(results) if !add_handles.is_empty() => {
+            let (added, failed) = note_results(results, add_handles);
+            sync_stats.num_added += added.len() as u64;
+            journal
+                .added
+                .extend(added.into_iter().map(|(_, note_id)| note_id));
+            sync_stats.num_added_errors += failed.len() as u64;
+            failed_notes.extend(failed.into_iter().map(|(note, err)| (note, "add", err)));
+        }
+        _ => m.suspend(|| info!("No new notes.")),
+    }
 
-        for chunk in add_actions_refs.chunks(chunk_size) {
-            let _ = match client.multi::<AddNote, BasicResponse>(chunk.to_vec()) {
-                // TODO: parse response to collect detailed errors for individual actions
-                Ok(response) => {
-                    global_pbar.inc(response.len().try_into().unwrap());
-                    let (success, fail): (Vec<&BasicResponse>, Vec<&BasicResponse>) =
-                        response.par_iter().partition(|resp| resp.error.is_none());
-                    sync_stats.num_added += success.len() as u64;
-                    sync_stats.num_added_errors += fail.len() as u64;
-                    Ok(())
-                }
-                Err(err) => Err(M2AnkiError::APIError(err)),
-            };
+    // Convert notes to the synced model
+    m.suspend(|| step.print_step(Some("Converting notes to the synced model"), Some(REPEAT)));
+    match &results {
+        Some(results) if !change_model_handles.is_empty() => {
+            let (converted, failed) = note_results(results, change_model_handles);
+            sync_stats.num_converted += converted.len() as u64;
+            journal
+                .converted
+                .extend(converted.iter().filter_map(|(note, _)| snapshot(note)));
+            sync_stats.num_converted_errors += failed.len() as u64;
+            failed_notes.extend(
+                failed
+                    .into_iter()
+                    .map(|(note, err)| (note, "change model", err)),
+            );
         }
-    } else {
-        m.suspend(|| info!("No new notes."));
+        _ => m.suspend(|| info!("No notes to convert.")),
     }
 
     // Update notes
     m.suspend(|| step.print_step(Some("Updating notes"), Some(REPEAT)));
-    if !operation_params.update.is_empty() {
-        let action = "updateNote";
-        let update_actions: Vec<Action<UpdateNoteFields>> = operation_params
-            .update
-            .par_iter()
-            .map(|update_note| Action::new(action, 6, update_note))
-            .collect();
-
-        let update_actions_refs: Vec<&Action<UpdateNoteFields>> =
-            update_actions.par_iter().collect();
-
-        let chunk_size = min(update_actions_refs.len(), 500);
-
-        for chunk in update_actions_refs.chunks(chunk_size) {
-            let _ = match client.multi::<UpdateNoteFields, BasicResponse>(chunk.to_vec()) {
-                // TODO: parse response to collect detailed errors for individual actions
-                Ok(response) => {
-                    global_pbar.inc(response.len().try_into().unwrap());
-                    let (success, fail): (Vec<&BasicResponse>, Vec<&BasicResponse>) =
-                        response.par_iter().partition(|resp| resp.error.is_none());
-                    sync_stats.num_updated += success.len() as u64;
-                    sync_stats.num_updated_errors += fail.len() as u64;
-                    Ok(())
-                }
-                Err(err) => Err(M2AnkiError::APIError(err)),
-            };
+    match &results {
+        Some(results) if !update_handles.is_empty() => {
+            let (updated, failed) = note_results(results, update_handles);
+            sync_stats.num_updated += updated.len() as u64;
+            journal
+                .updated
+                .extend(updated.iter().filter_map(|(note, _)| snapshot(note)));
+            sync_stats.num_updated_errors += failed.len() as u64;
+            failed_notes.extend(failed.into_iter().map(|(note, err)| (note, "update", err)));
         }
-    } else {
-        m.suspend(|| info!("No notes to update."));
+        _ => m.suspend(|| info!("No notes to update.")),
     }
 
     m.suspend(|| step.print_step(Some("Moving notes"), Some(SHUFFLE)));
-    if !operation_params.move_.is_empty() {
-        let action = "changeDeck";
-        let move_actions: Vec<Action<ChangeDeck>> = operation_params
-            .move_
-            .par_iter()
-            .map(|change_deck| Action::new(action, 6, change_deck))
-            .collect();
-
-        let move_actions_refs: Vec<&Action<ChangeDeck>> = move_actions.par_iter().collect();
-
-        let chunk_size = min(move_actions_refs.len(), 500);
-
-        for chunk in move_actions_refs.chunks(chunk_size) {
-            let _ = match client.multi::<ChangeDeck, BasicResponse>(chunk.to_vec()) {
-                // TODO: parse response to collect detailed errors for individual actions
-                Ok(response) => {
-                    global_pbar.inc(response.len().try_into().unwrap());
-                    let (success, fail): (Vec<&BasicResponse>, Vec<&BasicResponse>) =
-                        response.par_iter().partition(|resp| resp.error.is_none());
-                    sync_stats.num_moved += success.len() as u64;
-                    sync_stats.num_moved_errors += fail.len() as u64;
-                    Ok(())
-                }
-                Err(err) => Err(M2AnkiError::APIError(err)),
-            };
+    match &results {
+        Some(results) if !move_handles.is_empty() => {
+            let (moved, failed) = note_results(results, move_handles);
+            sync_stats.num_moved += moved.len() as u64;
+            journal
+                .moved
+                .extend(moved.iter().filter_map(|(note, _)| snapshot(note)));
+            sync_stats.num_moved_errors += failed.len() as u64;
+            failed_notes.extend(failed.into_iter().map(|(note, err)| (note, "move", err)));
         }
-    } else {
-        m.suspend(|| info!("No notes to move."));
+        _ => m.suspend(|| info!("No notes to move.")),
     }
 
-    // Delete removed notes
-    m.suspend(|| step.print_step(Some("Deleting notes"), Some(CROSS)));
+    // Remove notes whose callout no longer exists, failures are reported once the sync is done
+    let mut failed_cleanups: Vec<String> = Vec::new();
+    m.suspend(|| step.print_step(Some("Removing orphaned notes"), Some(CROSS)));
     if !operation_params.delete.is_empty() {
-        let _ = client.notes().delete_notes(&operation_params.delete);
-        sync_stats.num_deleted += operation_params.delete.len() as u64;
+        let delete_cards: Vec<&CardId> = operation_params
+            .delete
+            .iter()
+            .flat_map(|note| &note.cards)
+            .collect();
+        let result = match orphan_policy {
+            OrphanPolicy::Delete => client.notes().delete_notes(
+                &operation_params
+                    .delete
+                    .iter()
+                    .map(|note| &note.note_id)
+                    .collect(),
+            ),
+            OrphanPolicy::Trash => client
+                .decks()
+                .change_deck(delete_cards.clone(), &trash_deck),
+            OrphanPolicy::Suspend => client.cards().suspend(&delete_cards),
+        };
+        match result {
+            Ok(_) => {
+                sync_stats.num_removed += operation_params.delete.len() as u64;
+                let snapshots = operation_params.delete.iter().map(|note| {
+                    let deck = markdown_id_to_anki_deck
+                        .get(&note.markdown_id)
+                        .copied()
+                        .unwrap_or_default();
+                    NoteSnapshot::new(note, deck)
+                });
+                match orphan_policy {
+                    OrphanPolicy::Delete => journal.deleted.extend(snapshots),
+                    OrphanPolicy::Trash => journal.trashed.extend(snapshots),
+                    OrphanPolicy::Suspend => journal
+                        .suspended
+                        .extend(delete_cards.iter().map(|card| **card)),
+                }
+            }
+            Err(err) => {
+                m.suspend(|| error!("Failed to remove orphaned notes: {}", err));
+                sync_stats.num_removed_errors += operation_params.delete.len() as u64;
+                failed_cleanups.push(format!("removing orphaned notes ({})", err));
+            }
+        }
         global_pbar.inc(operation_params.delete.len().try_into().unwrap());
+    } else if prune {
+        m.suspend(|| info!("No notes to remove."));
     }
 
-    // TODO: find a way to delete empty decks
-
-    // Delete empty decks
+    // Delete the decks left empty by moved, renamed or removed markdown files
     m.suspend(|| step.print_step(Some("Deleting empty decks"), Some(CROSS)));
-    // if sync_stats.num_moved > 0 || sync_stats.num_deleted > 0 {
-    //     let anki_notes_in_deck = client.notes().get_notes_by_deck_name(&parent_deck)?;
-    //     let all_card_ids = anki_notes_in_deck
-    //         .par_iter()
-    //         .flat_map(|note| &note.cards)
-    //         .collect();
-    //     let anki_decks: HashMap<String, Vec<CardId>> = client.decks().get_decks(&all_card_ids)?;
-    //     let empty_decks: Vec<String> = anki_decks
-    //         .into_par_iter()
-    //         .filter_map(
-    //             |(name, cards)| {
-    //                 if cards.is_empty() { Some(name) } else { None }
-    //             },
-    //         )
-    //         .collect();
-    //     dbg!(&empty_decks);
-    //     client
-    //         .decks()
-    //         .delete_decks(empty_decks.iter().map(|name| name.as_str()).collect());
-    // }
+    if prune {
+        let empty_decks = find_empty_decks(client, &parent_deck, &source_decks, &HashSet::new())?;
+        if empty_decks.is_empty() {
+            m.suspend(|| info!("No empty decks to delete."));
+        } else {
+            match client
+                .decks()
+                .delete_decks(empty_decks.iter().map(|name| name.as_str()).collect())
+            {
+                Ok(_) => {
+                    m.suspend(|| info!("Deleted empty decks: {}", empty_decks.join(", ")));
+                    journal.deleted_decks = empty_decks;
+                }
+                Err(err) => {
+                    m.suspend(|| error!("Failed to delete empty decks: {}", err));
+                    failed_cleanups.push(format!("deleting empty decks ({})", err));
+                }
+            }
+        }
+    }
+
+    // Remember the synced notes, so that later edits made in Anki can be detected and unchanged
+    // notes can be skipped. Notes that were not written keep their previous state, and their files
+    // are synced again next time. Conflicts settled in favor of Anki are remembered like synced
+    // notes, so that they are only reported once.
+    let has_errors = sync_stats.num_added_errors
+        + sync_stats.num_converted_errors
+        + sync_stats.num_updated_errors
+        + sync_stats.num_moved_errors
+        + sync_stats.num_removed_errors
+        > 0
+        || !failed_cleanups.is_empty();
+    let unsynced_ids: HashSet<&String> = operation_params
+        .model_mismatches
+        .iter()
+        .map(|(note, _)| note.get_markdown_id())
+        .chain(
+            conflicted_ids
+                .iter()
+                .copied()
+                .filter(|_| conflict_policy == ConflictPolicy::Skip),
+        )
+        .collect();
+    let markdown_id_to_deck: HashMap<&String, &Deck> = decks
+        .iter()
+        .flat_map(|deck| {
+            deck.callouts
+                .iter()
+                .map(move |callout| (&callout.markdown_id, deck))
+        })
+        .collect();
+
+    let synced_note_ids: Vec<NoteId> = client
+        .notes()
+        .find_notes_ids_by_deck_name(&parent_deck)?
+        .into_iter()
+        .filter(|note_id| !known_note_ids.contains(note_id))
+        .collect();
+    let synced_notes = if synced_note_ids.is_empty() {
+        Vec::new()
+    } else {
+        client.notes().notes_info_by_id(&synced_note_ids)?
+    };
+
+    let mut previous_notes = std::mem::take(&mut state.notes);
+    state.notes = previous_notes
+        .extract_if(|_, note| known_note_ids.contains(&note.note_id))
+        .collect();
+    for note in synced_notes {
+        let Some(deck) = markdown_id_to_deck.get(&note.markdown_id) else {
+            continue;
+        };
+        let previous = previous_notes.get(&note.markdown_id);
+        let is_unsynced = unsynced_ids.contains(&note.markdown_id);
+        let mtime = match previous {
+            Some(previous) if is_unsynced => previous.mtime,
+            _ => note.mtime,
+        };
+        let hash = if is_unsynced || has_errors {
+            String::new()
+        } else {
+            note_hashes
+                .get(&note.markdown_id)
+                .cloned()
+                .unwrap_or_default()
+        };
+        state.notes.insert(
+            note.markdown_id,
+            NoteState {
+                note_id: note.note_id,
+                mtime,
+                hash,
+                deck: deck.qualified_name.clone(),
+                file: deck.source_file.clone(),
+            },
+        );
+    }
+
+    let unsynced_files: HashSet<&PathBuf> = unsynced_ids
+        .iter()
+        .filter_map(|markdown_id| markdown_id_to_deck.get(markdown_id))
+        .map(|deck| &deck.source_file)
+        .collect();
+    state.files = file_hashes
+        .into_iter()
+        .filter(|(path, _)| {
+            unchanged_files.contains(path) || (!has_errors && !unsynced_files.contains(path))
+        })
+        .collect();
+    state.settings = settings_hash;
+    state.save(input_dir)?;
+    journal.save(input_dir)?;
 
     // Report stats
     m.suspend(|| step.print_step(Some("Displaying stats and results:"), Some(BAR_CHART)));
     global_pbar.finish();
     println!("\nSync Stats:\n{}", sync_stats);
 
-    if !failed_notes.is_empty() {
-        warn!(
-            "Failed in {} decks, with a total of {} failed notes.",
-            failed_notes.len(),
-            failed_notes
-                .par_iter()
-                .map(|(_, item)| { item.len() })
-                .sum::<usize>(),
+    if !operation_params.conflicts.is_empty() {
+        let resolution = match conflict_policy {
+            ConflictPolicy::Markdown => "overwritten with the markdown content",
+            ConflictPolicy::Anki => "kept the content edited in Anki",
+            ConflictPolicy::Skip => "skipped",
+        };
+        println!(
+            "\nConflicts, edited in Anki since the last sync ({}):",
+            resolution
         );
+        for note in &operation_params.conflicts {
+            println!("  {} ({})", note.get_markdown_id(), note.get_deck_name());
+        }
+    }
 
-        let mut f = File::create(input_dir.join("failed_notes.json"))?;
-        let failed_hash_map: HashMap<PathBuf, Vec<(String, ModelType)>> = failed_notes
-            .into_par_iter()
-            .map(|(source, failed)| (source, failed))
-            .collect();
-        f.write_all(serde_json::to_string_pretty(&failed_hash_map)?.as_bytes())?;
+    // Failed notes are grouped by file in the report, a report left by a previous sync is removed
+    let failed_notes_path = input_dir.join("failed_notes.json");
+    if !failed_notes.is_empty() {
+        let mut failed_by_file: BTreeMap<PathBuf, Vec<FailedNote>> = BTreeMap::new();
+        for (note, operation, error) in &failed_notes {
+            let file = markdown_id_to_deck
+                .get(note.get_markdown_id())
+                .map(|deck| deck.source_file.clone())
+                .unwrap_or_default();
+            failed_by_file.entry(file).or_default().push(FailedNote {
+                markdown_id: note.get_markdown_id(),
+                deck: note.get_deck_name(),
+                operation,
+                error: error.clone(),
+            });
+        }
+
+        println!("\nFailed notes:");
+        for (file, failed) in &failed_by_file {
+            for note in failed {
+                println!(
+                    "  {} ({}) {}: {}",
+                    note.markdown_id,
+                    file.to_str().unwrap_or_default(),
+                    note.operation,
+                    note.error
+                );
+            }
+        }
+
+        let mut f = File::create(&failed_notes_path)?;
+        f.write_all(serde_json::to_string_pretty(&failed_by_file)?.as_bytes())?;
+        return Err(M2AnkiError::FailedNotes {
+            count: failed_notes.len(),
+            report: failed_notes_path,
+        });
+    } else if failed_notes_path.is_file() {
+        remove_file(&failed_notes_path)?;
+    }
+    if !failed_cleanups.is_empty() {
+        return Err(M2AnkiError::FailedCleanup(failed_cleanups));
     }
 
     step.print_step(Some("Done"), Some(SPARKLE));
@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::config::OrphanPolicy;
+
+/// Changes that a sync would make, computed without writing anything to Anki.
+#[derive(Debug, Default, Serialize)]
+pub struct SyncPlan {
+    /// Changes made to the model before syncing the notes.
+    pub model: Vec<String>,
+    /// Deck deleted before syncing when `--delete` is passed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete_deck: Option<String>,
+    /// How notes without a callout are removed, `None` when `--prune` is not passed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orphans: Option<OrphanPolicy>,
+    /// Deck to which removed notes are moved by the trash policy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trash_deck: Option<String>,
+    /// Empty decks under the parent deck that are deleted after syncing the notes.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub delete_decks: Vec<String>,
+    /// Planned note operations grouped by the name of the deck they apply to.
+    pub decks: BTreeMap<String, DeckPlan>,
+}
+
+/// Markdown IDs of the notes affected by each operation in a deck.
+#[derive(Debug, Default, Serialize)]
+pub struct DeckPlan {
+    pub add: Vec<String>,
+    pub update: Vec<String>,
+    #[serde(rename = "move")]
+    pub move_: Vec<PlannedMove>,
+    /// Notes without a callout that are removed according to the orphan policy.
+    pub delete: Vec<String>,
+    /// Notes without a callout that are left in Anki, since `--prune` is not passed.
+    pub orphaned: Vec<String>,
+    pub change_model: Vec<String>,
+    /// Notes edited in Anki since the last sync, they are only updated when markdown wins.
+    pub conflict: Vec<String>,
+    pub unchanged: usize,
+}
+
+/// Note moved into the deck of its plan.
+#[derive(Debug, Serialize)]
+pub struct PlannedMove {
+    pub markdown_id: String,
+    pub from: String,
+}
+
+impl SyncPlan {
+    /// Returns the plan of a deck, creating it if needed.
+    pub fn deck(&mut self, name: &str) -> &mut DeckPlan {
+        self.decks.entry(name.to_string()).or_default()
+    }
+}
+
+impl Display for SyncPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(deck) = &self.delete_deck {
+            writeln!(f, "Delete deck '{}' and all of its notes", deck)?;
+        }
+        for change in &self.model {
+            writeln!(f, "{}", change)?;
+        }
+        if !self.model.is_empty() || self.delete_deck.is_some() {
+            writeln!(f)?;
+        }
+
+        let headers = [
+            "Add",
+            "Update",
+            "Move",
+            "Remove",
+            "Orphaned",
+            "Convert",
+            "Conflict",
+            "Unchanged",
+        ];
+        let deck_width = self
+            .decks
+            .keys()
+            .map(|name| name.chars().count())
+            .chain([4])
+            .max()
+            .unwrap_or_default();
+        write!(f, "{:<deck_width$}", "Deck")?;
+        for header in headers {
+            write!(f, "  {:>9}", header)?;
+        }
+        writeln!(f)?;
+        for (name, deck) in &self.decks {
+            write!(f, "{:<deck_width$}", name)?;
+            for count in [
+                deck.add.len(),
+                deck.update.len(),
+                deck.move_.len(),
+                deck.delete.len(),
+                deck.orphaned.len(),
+                deck.change_model.len(),
+                deck.conflict.len(),
+                deck.unchanged,
+            ] {
+                write!(f, "  {:>9}", count)?;
+            }
+            writeln!(f)?;
+        }
+
+        // Destructive changes are listed one by one, so they can be reviewed
+        for (name, deck) in &self.decks {
+            for planned_move in &deck.move_ {
+                write!(
+                    f,
+                    "\nMove {} from '{}' to '{}'",
+                    planned_move.markdown_id, planned_move.from, name
+                )?;
+            }
+            for markdown_id in &deck.delete {
+                match (self.orphans.unwrap_or_default(), &self.trash_deck) {
+                    (OrphanPolicy::Trash, Some(trash_deck)) => write!(
+                        f,
+                        "\nMove {} from '{}' to '{}'",
+                        markdown_id, name, trash_deck
+                    )?,
+                    (OrphanPolicy::Suspend, _) => {
+                        write!(f, "\nSuspend {} in '{}'", markdown_id, name)?
+                    }
+                    _ => write!(f, "\nDelete {} from '{}'", markdown_id, name)?,
+                }
+            }
+            for markdown_id in &deck.orphaned {
+                write!(
+                    f,
+                    "\nOrphaned {} in '{}', pass --prune to remove it",
+                    markdown_id, name
+                )?;
+            }
+            for markdown_id in &deck.conflict {
+                write!(
+                    f,
+                    "\nConflict {} in '{}', edited in Anki",
+                    markdown_id, name
+                )?;
+            }
+        }
+        for deck in &self.delete_decks {
+            write!(f, "\nDelete empty deck '{}'", deck)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,243 @@
+use super::{anki_connect_client, load_anki_profile};
+use crate::anki_connect::anki_connect_client::params::Action;
+use crate::anki_connect::batch::BatchOptions;
+use crate::anki_connect::capabilities::API_VERSION;
+use crate::anki_connect::card::CardId;
+use crate::anki_connect::notes_client::params::{
+    AddNote, AddNoteNote, AddNoteOptions, DuplicateScopeOptions, UpdateNoteFields,
+    UpdateNoteFieldsNote, UpdateNoteModel, UpdateNoteModelNote,
+};
+use crate::anki_connect::{AnkiConnectClient, error::APIError, note::NoteId};
+use crate::cli::UndoArgs;
+use crate::config::Config;
+use crate::error::M2AnkiError;
+use crate::journal::{Journal, NoteSnapshot};
+use crate::progress::{CROSS, LOOKING_GLASS, REPEAT, SPARKLE, Step};
+use crate::state::SyncState;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use tracing::{error, info, warn};
+
+/// AnkiConnect actions used to revert a sync.
+const UNDO_ACTIONS: &[&str] = &[
+    "multi",
+    "createDeck",
+    "changeDeck",
+    "addNote",
+    "updateNote",
+    "unsuspend",
+    "deleteNotes",
+];
+
+fn snapshot_fields(note: &NoteSnapshot) -> HashMap<&str, &str> {
+    note.fields
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect()
+}
+
+fn snapshot_tags(note: &NoteSnapshot) -> Vec<&str> {
+    note.tags.iter().map(String::as_str).collect()
+}
+
+/// Sends the actions in batches and returns the number of failed actions.
+fn send_actions<P>(client: &AnkiConnectClient, action: &str, params: &[P]) -> usize
+where
+    P: Serialize + std::fmt::Debug,
+{
+    let actions: Vec<Action<P>> = params
+        .iter()
+        .map(|params| Action::new(action, API_VERSION, params))
+        .collect();
+    let responses = client.multi_batched(&actions, &BatchOptions::default(), &|_| ());
+    let errors: Vec<&String> = responses
+        .iter()
+        .filter_map(|response| response.error.as_ref())
+        .collect();
+    for err in &errors {
+        error!("Failed to revert '{}': {}", action, err);
+    }
+    errors.len()
+}
+
+/// Moves the cards of the notes back to the deck they were in, returns the number of notes that
+/// could not be moved.
+fn move_back(client: &AnkiConnectClient, notes: &[NoteSnapshot]) -> usize {
+    let mut decks: BTreeMap<&str, Vec<&NoteSnapshot>> = BTreeMap::new();
+    for note in notes {
+        decks.entry(note.deck.as_str()).or_default().push(note);
+    }
+    let mut num_errors = 0;
+    for (deck, notes) in decks {
+        let cards: Vec<&CardId> = notes.iter().flat_map(|note| &note.cards).collect();
+        if let Err(err) = client.decks().change_deck(cards, deck) {
+            error!("Failed to move notes back to '{}': {}", deck, err);
+            num_errors += notes.len();
+        }
+    }
+    num_errors
+}
+
+/// Reverts the most recent sync of the input directory, using its journal.
+pub fn undo(args: UndoArgs) -> Result<(), M2AnkiError> {
+    let input_dir = &args.input_dir;
+    let Some((journal_path, journal)) = Journal::latest(input_dir)? else {
+        warn!(
+            "No sync to undo in '{}'.",
+            input_dir.to_str().unwrap_or_default()
+        );
+        return Ok(());
+    };
+
+    if args.dry_run {
+        println!("{}", journal);
+        return Ok(());
+    }
+
+    let mut step = Step::new(1, 5);
+    step.print_step(Some("Connecting to Anki"), Some(LOOKING_GLASS));
+    let config = Config::find(None, input_dir)?;
+    let client = anki_connect_client(&args.anki_connect, &config)?;
+    if !client.test_connection().unwrap_or(false) {
+        error!("Cannot connect to Anki. Make sure it is running.");
+        return Err(M2AnkiError::APIError(APIError::FailedConnection(
+            "Cannot connect to Anki. Make sure it is running.".to_string(),
+        )));
+    }
+    load_anki_profile(&client, &args.anki_connect, &config, false)?;
+    client.require_actions(UNDO_ACTIONS)?;
+    if !journal.converted.is_empty() {
+        client.require_actions(&["updateNoteModel"])?;
+    }
+
+    // Changes are reverted in the reverse order of the sync, the added notes are deleted first
+    // so that they don't make the restored notes duplicates
+    let mut num_errors = 0;
+    step.print_step(Some("Deleting added notes"), Some(CROSS));
+    if !journal.added.is_empty() {
+        let added: Vec<&NoteId> = journal.added.iter().collect();
+        if let Err(err) = client.notes().delete_notes(&added) {
+            error!("Failed to delete added notes: {}", err);
+            num_errors += added.len();
+        }
+    }
+
+    step.print_step(Some("Restoring notes"), Some(REPEAT));
+    // Converted notes get back all of their fields with their previous model
+    let converted_ids: HashSet<&NoteId> =
+        journal.converted.iter().map(|note| &note.note_id).collect();
+    let tags: Vec<Vec<&str>> = journal.updated.iter().map(snapshot_tags).collect();
+    let update_notes: Vec<UpdateNoteFields> = journal
+        .updated
+        .iter()
+        .zip(&tags)
+        .filter(|(note, _)| !converted_ids.contains(&note.note_id))
+        .map(|(note, tags)| {
+            UpdateNoteFields::new(UpdateNoteFieldsNote::new(
+                &note.note_id,
+                snapshot_fields(note),
+                None,
+                None,
+                None,
+                Some(tags),
+            ))
+        })
+        .collect();
+    num_errors += send_actions(&client, "updateNote", &update_notes);
+    let update_note_models: Vec<UpdateNoteModel> = journal
+        .converted
+        .iter()
+        .map(|note| {
+            UpdateNoteModel::new(UpdateNoteModelNote::new(
+                &note.note_id,
+                &note.model_name,
+                snapshot_fields(note),
+                snapshot_tags(note),
+            ))
+        })
+        .collect();
+    num_errors += send_actions(&client, "updateNoteModel", &update_note_models);
+
+    step.print_step(Some("Moving notes back"), Some(REPEAT));
+    num_errors += move_back(&client, &journal.moved);
+
+    step.print_step(Some("Restoring removed notes"), Some(REPEAT));
+    let decks: BTreeSet<&str> = journal
+        .deleted_decks
+        .iter()
+        .map(String::as_str)
+        .chain(journal.deleted.iter().map(|note| note.deck.as_str()))
+        .collect();
+    for deck in decks {
+        if let Err(err) = client.decks().create_deck(deck) {
+            error!("Failed to create deck '{}': {}", deck, err);
+            num_errors += 1;
+        }
+    }
+    let add_notes: Vec<AddNote> = journal
+        .deleted
+        .iter()
+        .map(|note| {
+            AddNote::new(AddNoteNote::new(
+                &note.deck,
+                &note.model_name,
+                snapshot_fields(note),
+                AddNoteOptions::new(
+                    false,
+                    "deck",
+                    DuplicateScopeOptions::new(&note.deck, true, false),
+                ),
+                snapshot_tags(note),
+                None,
+                None,
+                None,
+            ))
+        })
+        .collect();
+    num_errors += send_actions(&client, "addNote", &add_notes);
+    num_errors += move_back(&client, &journal.trashed);
+    if !journal.suspended.is_empty() {
+        let cards: Vec<&CardId> = journal.suspended.iter().collect();
+        if let Err(err) = client.cards().unsuspend(&cards) {
+            error!("Failed to unsuspend cards: {}", err);
+            num_errors += cards.len();
+        }
+    }
+
+    // The reverted notes are synced again by the next sync, and are not reported as conflicts
+    let mut state = SyncState::load(input_dir)?;
+    let reverted_ids: HashSet<&NoteId> = journal
+        .added
+        .iter()
+        .chain(
+            [
+                &journal.converted,
+                &journal.updated,
+                &journal.moved,
+                &journal.deleted,
+                &journal.trashed,
+            ]
+            .into_iter()
+            .flatten()
+            .map(|note| &note.note_id),
+        )
+        .collect();
+    state
+        .notes
+        .retain(|_, note| !reverted_ids.contains(&note.note_id));
+    state.settings.clear();
+    state.save(input_dir)?;
+
+    println!("{}", journal);
+    if num_errors > 0 {
+        return Err(M2AnkiError::FailedUndo {
+            count: num_errors,
+            journal: journal_path,
+        });
+    }
+    Journal::mark_undone(&journal_path)?;
+    info!("Reverted the last sync.");
+
+    step.print_step(Some("Done"), Some(SPARKLE));
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use thiserror::Error;
+
+use crate::model::{CardDirection, TtsOptions};
+use crate::state::ConflictPolicy;
+
+/// Name of the config file that is looked up inside the input directory.
+pub const CONFIG_FILE_NAME: &str = "md2anki.toml";
+
+/// Largest fraction of the notes of the deck that a sync removes without being allowed explicitly.
+pub const DEFAULT_MAX_DELETE_FRACTION: f64 = 0.25;
+
+/// What to do with the notes in Anki whose callout no longer exists.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Display, ValueEnum, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum OrphanPolicy {
+    /// Delete the notes together with their review history
+    #[default]
+    Delete,
+    /// Move the cards of the notes to the trash deck
+    Trash,
+    /// Suspend the cards of the notes
+    Suspend,
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("cannot read config file '{0}': {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("cannot parse config file '{0}': {1}")]
+    Parse(PathBuf, toml::de::Error),
+    #[error("invalid configuration: {0}")]
+    Invalid(String),
+    #[error("profile '{0}' is not defined in the config file")]
+    UnknownProfile(String),
+}
+
+/// Settings read from a `md2anki.toml` file, values passed on the command line take precedence.
+///
+/// ```toml
+/// directions = ["recognition", "recall"]
+/// on-conflict = "skip"
+/// prune = true
+/// orphans = "trash"
+/// trash-deck = "Korean Trash"
+/// max-delete-fraction = 0.1
+/// batch-size = 100
+/// retries = 5
+/// exclude = ["Templates"]
+/// anki-url = "http://localhost"
+/// anki-port = 8765
+/// anki-key = "secret"
+/// anki-profile = "User 1"
+/// anki-sync = true
+///
+/// [tts]
+/// lang = "ko_KR"
+/// voices = ["Apple_Yuna", "Microsoft_Heami"]
+/// fields = ["Front"]
+///
+/// [profiles.chinese]
+/// input = "Chinese"
+/// deck = "Chinese"
+/// lang = "zh"
+/// ```
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    pub directions: Option<Vec<CardDirection>>,
+    pub tts: Option<TtsOptions>,
+    pub on_conflict: Option<ConflictPolicy>,
+    pub prune: Option<bool>,
+    pub orphans: Option<OrphanPolicy>,
+    pub trash_deck: Option<String>,
+    pub max_delete_fraction: Option<f64>,
+    pub batch_size: Option<usize>,
+    pub retries: Option<u32>,
+    /// Files and directories, relative to the input directory, that are not synced.
+    pub exclude: Vec<PathBuf>,
+    pub anki_url: Option<String>,
+    pub anki_port: Option<u32>,
+    pub anki_key: Option<String>,
+    /// Anki profile loaded before anything is read from or written to Anki.
+    pub anki_profile: Option<String>,
+    /// Whether the collection is synced with AnkiWeb at the end of a successful sync.
+    pub anki_sync: Option<bool>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// Input directory and deck synced together by `sync --profile`, the other settings of the config
+/// file are shared by every profile.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Profile {
+    /// Directory searched for markdown files, relative to the config file.
+    pub input: PathBuf,
+    pub deck: Option<String>,
+    pub model: Option<String>,
+    pub model_name: Option<String>,
+    /// CSS file, relative to the config file.
+    pub css: Option<PathBuf>,
+    pub lang: Option<String>,
+    pub directions: Option<Vec<CardDirection>>,
+    pub tts: Option<TtsOptions>,
+    /// Files and directories, relative to the input directory, that are not synced.
+    pub exclude: Vec<PathBuf>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let content =
+            read_to_string(path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+        toml::from_str(&content).map_err(|err| ConfigError::Parse(path.to_path_buf(), err))
+    }
+
+    /// Loads the given config file, or the `md2anki.toml` file inside of `input_dir` if it
+    /// exists. Falls back to the default config otherwise.
+    pub fn find(config_file: Option<&Path>, input_dir: &Path) -> Result<Self, ConfigError> {
+        match config_file {
+            Some(path) => Self::load(path),
+            None => {
+                let path = input_dir.join(CONFIG_FILE_NAME);
+                if path.is_file() {
+                    Self::load(&path)
+                } else {
+                    Ok(Self::default())
+                }
+            }
+        }
+    }
+}
@@ -5,8 +5,10 @@ use std::{
 
 use strum::Display;
 use thiserror::Error;
+use tracing::warn;
 
 use crate::callout::{Callout, error::CalloutError};
+use crate::frontmatter::FrontMatter;
 
 #[derive(Error, Display, Debug)]
 pub enum DeckError {
@@ -18,6 +20,7 @@ pub enum DeckError {
 pub struct Deck {
     pub source_file: PathBuf,
     pub qualified_name: String,
+    pub front_matter: FrontMatter,
     pub callouts: Vec<Callout>,
     pub failed: Vec<(String, CalloutError)>,
 }
@@ -57,21 +60,31 @@ impl TryFrom<&PathBuf> for Deck {
     type Error = DeckError;
 
     fn try_from(value: &PathBuf) -> Result<Self, Self::Error> {
-        let callouts_results = Callout::extract_callouts(value);
-        if !callouts_results.failed.is_empty() {
-            // TODO: extract line numbers to report more accurate errors about why making some
-            // caloluts failed.
-            let content: String = match read_to_string(value) {
-                Ok(text) => text,
-                Err(_) => "".to_string(),
-            };
-            if !content.is_empty() {}
-        }
-        Ok(Deck {
-            source_file: value.clone(),
+        let content = read_to_string(value)?;
+        Ok(Deck::from_content(value, &content))
+    }
+}
+
+impl Deck {
+    /// Creates a deck from the content of a markdown file that was already read.
+    pub fn from_content(value: &Path, content: &str) -> Self {
+        let front_matter = FrontMatter::parse(content).unwrap_or_else(|err| {
+            warn!(
+                "Ignoring invalid front matter in '{}': {}",
+                value.to_str().unwrap_or_default(),
+                err
+            );
+            FrontMatter::default()
+        });
+        let callouts_results = Callout::extract_callouts_from_str(content);
+        // TODO: extract line numbers to report more accurate errors about why making some
+        // caloluts failed.
+        Deck {
+            source_file: value.to_path_buf(),
             qualified_name: "".to_string(),
+            front_matter,
             callouts: callouts_results.callouts,
             failed: callouts_results.failed,
-        })
+        }
     }
 }
@@ -1,8 +1,10 @@
 use std::any::Any;
 use std::error::Error as StdError;
 use std::io::Error as IOError;
+use std::path::PathBuf;
 
 use crate::anki_connect::error::APIError;
+use crate::config::ConfigError;
 use crate::deck::DeckError;
 use serde_json::Error as SerdeJsonError;
 use thiserror::Error;
@@ -15,12 +17,26 @@ pub type GenericSendStatic = Box<dyn StdError + Send + 'static>;
 pub enum M2AnkiError {
     #[error("error from AnkiConnect API: '{0}'")]
     APIError(#[from] APIError),
+    #[error("expected the Anki profile '{expected}' to be open, but '{found}' is")]
+    AnkiProfileMismatch { expected: String, found: String },
+    #[error("the Anki profile '{0}' does not exist")]
+    AnkiProfileNotFound(String),
+    #[error("{0}")]
+    ConfigError(#[from] ConfigError),
     // #[error("Anki note not found with ID: '{0}'")]
     // AnkiNoteNotFound(String),
     // #[error("Card ID not found: '{0}'")]
     // CardIdNotFound(String),
     #[error("Deck error: {0}")]
     DeckError(#[from] DeckError),
+    #[error("{count} notes failed to sync, see '{}'", report.display())]
+    FailedNotes { count: usize, report: PathBuf },
+    #[error("failed to sync the profiles: {}", .0.join(", "))]
+    FailedProfiles(Vec<String>),
+    #[error("the notes were synced, but failed {}", .0.join(" and "))]
+    FailedCleanup(Vec<String>),
+    #[error("{count} changes could not be reverted, the journal '{}' is kept", journal.display())]
+    FailedUndo { count: usize, journal: PathBuf },
     #[error("cannot find deck with name: '{0}'")]
     DeckNameNotFound(String),
     #[error("error: {0}")]
@@ -39,6 +55,22 @@ pub enum M2AnkiError {
     NoteIdNotFound(String),
     #[error("error")]
     ProgressBarError,
+    #[error(
+        "refusing to remove {count} of the {total} notes in the deck, which is more than the allowed fraction of {max_fraction}. Pass --max-delete-fraction to allow it."
+    )]
+    TooManyDeletions {
+        count: usize,
+        total: usize,
+        max_fraction: f64,
+    },
+    #[error(
+        "removing the templates {} from model '{model}' deletes their cards in every deck using it, pass --remove-templates to allow it",
+        .templates.join(", ")
+    )]
+    TemplateRemoval {
+        model: String,
+        templates: Vec<String>,
+    },
     #[error("JSON parsing error: '{0}'")]
     SerdeJsonError(#[from] SerdeJsonError),
     #[error("thread panicked: '{0:?}'")]
@@ -6,7 +6,9 @@ use indicatif::ProgressBar;
 
 pub fn find_markdown_files(input_dir: &PathBuf) -> Result<Vec<PathBuf>, IOError> {
     let pb = ProgressBar::new_spinner();
+    // Sorted, so that the files are synced in the same order on every run
     Ok(WalkDir::new(input_dir)
+        .sort(true)
         .into_iter()
         .map(|entry| {
             pb.tick();
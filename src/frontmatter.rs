@@ -0,0 +1,94 @@
+use serde::Deserialize;
+
+use crate::model::{CardDirection, TtsOptions};
+
+/// Settings read from the YAML front matter of a markdown file, they only apply to the callouts of
+/// that file.
+///
+/// ```yaml
+/// ---
+/// md2anki-directions: [recognition]
+/// md2anki-tts-lang: zh_CN
+/// md2anki-tts-voices: [Apple_Tingting]
+/// ---
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct FrontMatter {
+    /// Directions of the cards generated for the notes of the file.
+    #[serde(rename = "md2anki-directions")]
+    pub directions: Option<Vec<CardDirection>>,
+    /// Locale of the text read out by TTS, e.g. `zh_CN`.
+    #[serde(rename = "md2anki-tts-lang")]
+    pub tts_lang: Option<String>,
+    #[serde(rename = "md2anki-tts-voices")]
+    pub tts_voices: Option<Vec<String>>,
+    #[serde(rename = "md2anki-tts-speed")]
+    pub tts_speed: Option<f32>,
+    /// Names of the fields read out by TTS.
+    #[serde(rename = "md2anki-tts-fields")]
+    pub tts_fields: Option<Vec<String>>,
+}
+
+impl FrontMatter {
+    /// Returns the YAML between the `---` delimiters at the start of a markdown document.
+    pub fn extract(content: &str) -> Option<&str> {
+        let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+        let rest = content
+            .strip_prefix("---\n")
+            .or_else(|| content.strip_prefix("---\r\n"))?;
+        let mut offset = 0;
+        for line in rest.split_inclusive('\n') {
+            if line.trim_end() == "---" {
+                return Some(&rest[..offset]);
+            }
+            offset += line.len();
+        }
+        None
+    }
+
+    /// Returns the TTS options of the notes of the file, the settings that are not given in the
+    /// front matter are taken from `model_tts`. `None` if the file doesn't override TTS.
+    pub fn tts(&self, model_tts: Option<&TtsOptions>) -> Option<TtsOptions> {
+        if self.tts_lang.is_none()
+            && self.tts_voices.is_none()
+            && self.tts_speed.is_none()
+            && self.tts_fields.is_none()
+        {
+            return None;
+        }
+        let mut tts = model_tts.cloned().unwrap_or_default();
+        if let Some(lang) = &self.tts_lang {
+            tts.lang = lang.clone();
+            // The voices of the model are for another language
+            if model_tts.is_some_and(|model_tts| model_tts.lang != *lang) {
+                tts.voices.clear();
+            }
+        }
+        if let Some(voices) = &self.tts_voices {
+            tts.voices = voices.clone();
+        }
+        if self.tts_speed.is_some() {
+            tts.speed = self.tts_speed;
+        }
+        if let Some(fields) = &self.tts_fields {
+            tts.fields = fields.clone();
+        }
+        Some(tts)
+    }
+
+    /// Removes the TTS settings, so that the notes of the file use the TTS of the model.
+    pub fn clear_tts(&mut self) {
+        self.tts_lang = None;
+        self.tts_voices = None;
+        self.tts_speed = None;
+        self.tts_fields = None;
+    }
+
+    pub fn parse(content: &str) -> Result<Self, serde_yaml::Error> {
+        match Self::extract(content) {
+            Some(yaml) if !yaml.trim().is_empty() => serde_yaml::from_str(yaml),
+            _ => Ok(Self::default()),
+        }
+    }
+}
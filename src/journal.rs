@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::{File, create_dir_all, read_dir, read_to_string, rename};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::anki_connect::{
+    AnkiConnectClient, card::CardId, note::NoteId, notes_client::responses::NoteInfo,
+};
+use crate::error::M2AnkiError;
+use crate::state::STATE_DIR_NAME;
+
+const JOURNAL_DIR_NAME: &str = "journal";
+const UNDONE_SUFFIX: &str = ".undone.json";
+
+/// Record of the changes applied to Anki by a sync, used by `undo` to revert them.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Journal {
+    /// Unix time at which the sync started, in milliseconds.
+    pub timestamp: u128,
+    /// Parent deck of the sync.
+    pub deck: String,
+    /// Notes created by the sync.
+    pub added: Vec<NoteId>,
+    /// Notes converted to the synced model, as they were before.
+    pub converted: Vec<NoteSnapshot>,
+    /// Updated notes, as they were before.
+    pub updated: Vec<NoteSnapshot>,
+    /// Moved notes, with the deck they were in before.
+    pub moved: Vec<NoteSnapshot>,
+    /// Deleted notes, they are added again by `undo`, without their review history.
+    pub deleted: Vec<NoteSnapshot>,
+    /// Notes moved to the trash deck, with the deck they were in before.
+    pub trashed: Vec<NoteSnapshot>,
+    /// Cards suspended by the sync.
+    pub suspended: Vec<CardId>,
+    /// Empty decks deleted by the sync.
+    pub deleted_decks: Vec<String>,
+}
+
+/// Content of a note in Anki before it was changed by a sync.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteSnapshot {
+    pub note_id: NoteId,
+    pub deck: String,
+    pub model_name: String,
+    pub fields: Vec<(String, String)>,
+    pub tags: Vec<String>,
+    pub cards: Vec<CardId>,
+}
+
+impl NoteSnapshot {
+    pub fn new(note: &NoteInfo, deck: &str) -> Self {
+        NoteSnapshot {
+            note_id: note.note_id,
+            deck: deck.to_string(),
+            model_name: note.model_name.clone(),
+            fields: note.fields.clone(),
+            tags: note.tags.clone(),
+            cards: note.cards.clone(),
+        }
+    }
+
+    /// Takes snapshots of notes whose deck is not known yet, the deck of their first card is used.
+    pub fn from_notes(
+        client: &AnkiConnectClient,
+        notes: &[NoteInfo],
+    ) -> Result<Vec<Self>, M2AnkiError> {
+        let cards: Vec<&CardId> = notes.iter().filter_map(|note| note.cards.first()).collect();
+        if cards.is_empty() {
+            return Ok(Vec::new());
+        }
+        let card_decks: HashMap<CardId, String> = client
+            .decks()
+            .get_decks(&cards)?
+            .into_iter()
+            .flat_map(|(deck, cards)| cards.into_iter().map(move |card| (card, deck.clone())))
+            .collect();
+        Ok(notes
+            .iter()
+            .map(|note| {
+                let deck = note
+                    .cards
+                    .first()
+                    .and_then(|card| card_decks.get(card))
+                    .map_or("", |deck| deck.as_str());
+                NoteSnapshot::new(note, deck)
+            })
+            .collect())
+    }
+}
+
+impl Journal {
+    pub fn new(deck: &str) -> Self {
+        Journal {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_millis()),
+            deck: deck.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn dir(input_dir: &Path) -> PathBuf {
+        input_dir.join(STATE_DIR_NAME).join(JOURNAL_DIR_NAME)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.converted.is_empty()
+            && self.updated.is_empty()
+            && self.moved.is_empty()
+            && self.deleted.is_empty()
+            && self.trashed.is_empty()
+            && self.suspended.is_empty()
+            && self.deleted_decks.is_empty()
+    }
+
+    /// Writes the journal to `.md2anki/journal/<timestamp>.json`, replacing the previous version
+    /// of the same journal.
+    pub fn save(&self, input_dir: &Path) -> Result<(), M2AnkiError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let dir = Self::dir(input_dir);
+        create_dir_all(&dir)?;
+        let mut f = File::create(dir.join(format!("{}.json", self.timestamp)))?;
+        f.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Loads the journal of the most recent sync that was not undone yet.
+    pub fn latest(input_dir: &Path) -> Result<Option<(PathBuf, Self)>, M2AnkiError> {
+        let dir = Self::dir(input_dir);
+        if !dir.is_dir() {
+            return Ok(None);
+        }
+        let latest = read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter_map(|path| {
+                let name = path.file_name()?.to_str()?;
+                if name.ends_with(UNDONE_SUFFIX) {
+                    return None;
+                }
+                let timestamp = name.strip_suffix(".json")?.parse::<u128>().ok()?;
+                Some((timestamp, path))
+            })
+            .max_by_key(|(timestamp, _)| *timestamp);
+        match latest {
+            Some((_, path)) => {
+                let journal = serde_json::from_str(&read_to_string(&path)?)?;
+                Ok(Some((path, journal)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Marks the journal file as undone, so that the next `undo` reverts the sync before it.
+    pub fn mark_undone(path: &Path) -> Result<(), M2AnkiError> {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".json"))
+            .unwrap_or_default();
+        rename(
+            path,
+            path.with_file_name(format!("{}{}", name, UNDONE_SUFFIX)),
+        )?;
+        Ok(())
+    }
+}
+
+impl Display for Journal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let changes = [
+            (self.added.len(), "Delete added notes"),
+            (
+                self.converted.len(),
+                "Convert notes back to their previous model",
+            ),
+            (self.updated.len(), "Restore the fields of updated notes"),
+            (self.moved.len(), "Move notes back to their previous deck"),
+            (
+                self.deleted.len(),
+                "Add deleted notes back, without their review history",
+            ),
+            (self.trashed.len(), "Move notes back from the trash deck"),
+            (self.suspended.len(), "Unsuspend cards"),
+            (self.deleted_decks.len(), "Create deleted empty decks"),
+        ];
+        write!(f, "Sync of '{}':", self.deck)?;
+        for (count, change) in changes.iter().filter(|(count, _)| *count > 0) {
+            write!(f, "\n  {:>6}  {}", count, change)?;
+        }
+        Ok(())
+    }
+}
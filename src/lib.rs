@@ -0,0 +1,23 @@
+//! Syncs the callouts of markdown notes with Anki through AnkiConnect. The `md2anki` binary is a
+//! thin wrapper around [`commands`], and [`anki_connect`] can be used to talk to Anki directly.
+#[cfg(all(feature = "ureq_blocking", feature = "reqwest_blocking"))]
+compile_error!("Only one of `ureq_blocking` or `reqwest_blocking` features can be enabled.");
+pub mod anki_connect;
+mod callout;
+pub mod cli;
+pub mod commands;
+mod config;
+mod deck;
+mod error;
+mod find_markdown_files;
+mod frontmatter;
+mod journal;
+mod macros;
+mod model;
+mod note_operation;
+mod progress;
+mod state;
+mod style;
+
+use crate::callout::Callout;
+use crate::find_markdown_files::find_markdown_files;
@@ -1,31 +1,14 @@
-// #![allow(unused)]
-#[cfg(all(feature = "ureq_blocking", feature = "reqwest_blocking"))]
-compile_error!("Only one of `ureq_blocking` or `reqwest_blocking` features can be enabled.");
-mod anki_connect;
-mod callout;
-mod cli;
-mod commands;
-mod deck;
-mod error;
-mod find_markdown_files;
-mod macros;
-mod model;
-mod note_operation;
-mod progress;
-
-use crate::callout::Callout;
-use crate::cli::{Cli, Commands};
-use crate::commands::{create_markdown_anki_cards_file, sync};
-use crate::error::M2AnkiError;
-use crate::find_markdown_files::find_markdown_files;
+use markdown_to_anki::cli::{Cli, Commands};
+use markdown_to_anki::commands::{create_markdown_anki_cards_file, open, pull, sync, undo};
 
 use std::path::PathBuf;
+use std::process::ExitCode;
 use tracing::error;
 use tracing_subscriber::FmtSubscriber;
 
 use clap::Parser;
 
-fn main() -> Result<(), M2AnkiError> {
+fn main() -> ExitCode {
     let args = Cli::parse();
 
     let verbosity = match args.verbose + 2 {
@@ -38,25 +21,31 @@ fn main() -> Result<(), M2AnkiError> {
 
     let subscriber = FmtSubscriber::builder()
         .with_max_level(verbosity) // Or your desired level
+        .with_writer(std::io::stderr)
         .finish();
     tracing::subscriber::set_global_default(subscriber)
         .expect("Failed to set global default subscriber");
 
-    match args.command {
+    let result = match args.command {
         Commands::ObsidianToAnki {
             input_dir,
             output_file,
         } => {
             let output_file_path: PathBuf =
                 output_file.map_or_else(|| input_dir.join("Anki cards.md"), |p| p.to_path_buf());
-            create_markdown_anki_cards_file(&input_dir, output_file_path)?
+            create_markdown_anki_cards_file(&input_dir, output_file_path)
         }
-        Commands::Sync(sync_args) => match sync(sync_args) {
-            Ok(_) => (),
-            Err(err) => error!("{:?}", err),
-        },
-        // _ => unreachable!(),
-    }
+        Commands::Sync(sync_args) => sync(*sync_args),
+        Commands::Pull(pull_args) => pull(pull_args),
+        Commands::Undo(undo_args) => undo(undo_args),
+        Commands::Open(open_args) => open(open_args),
+    };
 
-    Ok(())
+    match result {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(err) => {
+            error!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
 }
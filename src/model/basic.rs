@@ -1,10 +1,10 @@
 use crate::{
     anki_connect::{
-        models_client::params::CreateModel,
+        models_client::params::{CreateModel, UpdateModelTemplates, UpdateModelTemplatesModel},
         note::NoteId,
         notes_client::params::{
             AddNoteNote, AddNoteOptions, DuplicateScopeOptions, UpdateNoteFields,
-            UpdateNoteFieldsNote,
+            UpdateNoteFieldsNote, UpdateNoteModel, UpdateNoteModelNote,
         },
     },
     note_operation::NoteOperation,
@@ -16,7 +16,7 @@ use serde::Serialize;
 
 use crate::callout::Callout;
 
-use super::InternalModelMethods;
+use super::{CardDirection, CardTemplate, InternalModelMethods, ModelOptions, TtsOptions};
 
 #[derive(Debug, Default, Clone, Serialize)]
 pub struct Basic<'a> {
@@ -25,130 +25,185 @@ pub struct Basic<'a> {
     markdown_id: String,
     front: String,
     back: String,
+    tts: String,
+    skipped: Vec<CardDirection>,
 }
 
-impl<'a> InternalModelMethods<'a> for Basic<'a> {
-    fn from_callout(
-        &self,
-        callout: &Callout,
-        header_lang: Option<&str>,
-        deck_name: &'a str,
-    ) -> Self {
-        Basic {
-            deck_name: &deck_name,
-            operation: callout.operation,
-            markdown_id: callout.markdown_id.to_owned(),
-            front: callout.header.clone(),
-            back: callout.content_to_html(header_lang),
-        }
-    }
+const FIELD_NAMES: &[&str] = &[
+    "MarkdownID",
+    "Front",
+    "Back",
+    "Audio",
+    "Tts",
+    "SkipRecognition",
+    "SkipRecall",
+    "SkipListen",
+];
 
-    fn to_create_model(&self, model_name: &'a str, css: Option<&'a str>) -> CreateModel<'a> {
-        let templates = [
-            [
-                ("Name", "Recognition"),
-                (
-                    "Front",
+/// Returns the templates of the cards generated for each note.
+fn card_templates(options: &ModelOptions) -> Vec<CardTemplate> {
+    // Notes of files overriding TTS carry their own tags in the `Tts` field
+    let file_tts = r#"{{#Tts}}
+<br>
+<div class="center">{{Tts}}</div>
+{{/Tts}}"#;
+    let tts = match &options.tts {
+        Some(tts) => format!(
+            "\n{}\n{{{{^Tts}}}}\n<br>\n{}\n{{{{/Tts}}}}",
+            file_tts,
+            tts.to_template()
+        ),
+        None => format!("\n{}", file_tts),
+    };
+
+    options
+        .directions
+        .iter()
+        .map(|direction| {
+            let (front, back) = match direction {
+                CardDirection::Recognition => (
                     r#"<br>
 <div class="center">{{Front}}</div>
 <br>
-{{Audio}}
-<br>
-<div class="center">TTS M:{{tts ko_KR voices=com.samsung.SMT-ko-KR-SMTg01,Microsoft_Heami:Front}}</div>
-<br>
-TTS W: {{tts ko_KR voices=com.samsung.SMT-ko-KR-SMTl01:Korean}}"#,
-                ),
-                (
-                    "Back",
+{{Audio}}"#
+                        .to_string()
+                        + &tts,
                     r#"{{FrontSide}}
 
 <hr id=answer>
 
-<div class="center">{{Back}}</div>
-<br>
-<!--{{Audio}}
-<br>
-<div class="center">TTS M:{{tts ko_KR voices=com.samsung.SMT-ko-KR-SMTg01,Microsoft_Heami:Front}}</div>
-<br>
-TTS W: {{tts ko_KR voices=com.samsung.SMT-ko-KR-SMTl01:Korean}}-->"#,
+<div class="center">{{Back}}</div>"#
+                        .to_string(),
                 ),
-            ],
-            [
-                ("Name", "Recall"),
-                (
-                    "Front",
+                CardDirection::Recall => (
                     r#"<br>
-<div class="center">{{Back}}</div>"#,
-                ),
-                (
-                    "Back",
+<div class="center">{{Back}}</div>"#
+                        .to_string(),
                     r#"{{FrontSide}}
 
 <hr id=answer>
 
 <div class="center">{{Front}}</div>
-{{Audio}}
-<br>
-<div class="center">TTS M:{{tts ko_KR voices=com.samsung.SMT-ko-KR-SMTg01,Microsoft_Heami:Front}}</div>
-<br>
-TTS W: {{tts ko_KR voices=com.samsung.SMT-ko-KR-SMTl01:Korean}}"#,
+{{Audio}}"#
+                        .to_string()
+                        + &tts,
                 ),
-            ],
-            [
-                ("Name", "Listen"),
-                (
-                    "Front",
+                CardDirection::Listen => (
                     r#"<br>
-{{Audio}}
-<br>
-<div class="center">TTS M:{{tts ko_KR voices=com.samsung.SMT-ko-KR-SMTg01,Microsoft_Heami:Front}}</div>
-<br>
-TTS W: {{tts ko_KR voices=com.samsung.SMT-ko-KR-SMTl01:Korean}}"#,
-                ),
-                (
-                    "Back",
+{{Audio}}"#
+                        .to_string()
+                        + &tts,
                     r#"{{FrontSide}}
 
 <hr id=answer>
 
 <div class="center">{{Front}}</div>
 <br>
-<div class="center">{{Back}}</div>
-<br>
-<!--{{Audio}}
-{{tts ko_KR voices=com.samsung.SMT-ko-KR-SMTg01,Microsoft_Heami:Front}}
-{{Back}}-->"#,
+<div class="center">{{Back}}</div>"#
+                        .to_string(),
                 ),
-            ],
-        ];
+            };
+            CardTemplate::new(*direction, direction.wrap_front(&front), back)
+        })
+        .collect()
+}
 
-        let card_templates = templates
+impl<'a> InternalModelMethods<'a> for Basic<'a> {
+    fn from_callout(
+        &self,
+        callout: &Callout,
+        header_lang: Option<&str>,
+        deck_name: &'a str,
+        directions: &[CardDirection],
+        tts: Option<&TtsOptions>,
+    ) -> Self {
+        let front = callout.header.clone();
+        let back = callout.content_to_html(header_lang);
+        let tts = tts
+            .map(|tts| {
+                tts.fields
+                    .iter()
+                    .filter_map(|field| match field.as_str() {
+                        "Front" => Some(&front),
+                        "Back" => Some(&back),
+                        _ => None,
+                    })
+                    .filter(|text| !text.is_empty())
+                    .map(|text| tts.to_av_tag(text))
+                    .collect::<Vec<_>>()
+                    .join("\n<br>\n")
+            })
+            .unwrap_or_default();
+        Basic {
+            deck_name: &deck_name,
+            operation: callout.operation,
+            markdown_id: callout.markdown_id.to_owned(),
+            front,
+            back,
+            tts,
+            skipped: CardDirection::all()
+                .into_iter()
+                .filter(|direction| !directions.contains(direction))
+                .collect(),
+        }
+    }
+
+    fn to_create_model(
+        &self,
+        model_name: &'a str,
+        css: Option<&'a str>,
+        options: &ModelOptions,
+    ) -> CreateModel<'a> {
+        let card_templates = card_templates(options)
             .into_par_iter()
             .map(|template| {
-                template
-                    .into_par_iter()
-                    .map(|(k, v)| (Cow::from(k), Cow::from(v)))
-                    .collect::<HashMap<Cow<'a, str>, Cow<'a, str>>>()
+                HashMap::from([
+                    (Cow::from("Name"), Cow::from(template.direction.to_string())),
+                    (Cow::from("Front"), Cow::from(template.front)),
+                    (Cow::from("Back"), Cow::from(template.back)),
+                ])
             })
             .collect::<Vec<_>>();
 
         CreateModel::new(
             model_name,
-            vec!["MarkdownID", "Front", "Back", "Audio"],
+            FIELD_NAMES.to_vec(),
             css,
             Some(false),
             card_templates,
         )
     }
 
+    fn to_update_templates(
+        &self,
+        model_name: &'a str,
+        options: &ModelOptions,
+    ) -> UpdateModelTemplates<'a> {
+        let templates = card_templates(options)
+            .into_par_iter()
+            .map(|template| {
+                (
+                    template.direction.to_string(),
+                    HashMap::from([("Front", template.front), ("Back", template.back)]),
+                )
+            })
+            .collect();
+
+        UpdateModelTemplates::new(UpdateModelTemplatesModel::new(model_name, templates))
+    }
+
+    fn get_card_templates(&self, options: &ModelOptions) -> Vec<CardTemplate> {
+        card_templates(options)
+    }
+
+    fn get_field_names(&self) -> &'static [&'static str] {
+        FIELD_NAMES
+    }
+
     fn to_update_note(&'a self, note_id: &'a NoteId) -> UpdateNoteFields<'a> {
-        let mut field_values: HashMap<&'a str, &'a str> = HashMap::with_capacity(3);
-        field_values.insert("MarkdownID", self.markdown_id.as_str());
-        field_values.insert("Front", self.front.as_str());
-        field_values.insert("Back", self.back.as_str());
         UpdateNoteFields::new(UpdateNoteFieldsNote::new(
             note_id,
-            field_values,
+            self.get_fields(),
             self.get_audio(),
             // None,
             // video,
@@ -160,24 +215,42 @@ TTS W: {{tts ko_KR voices=com.samsung.SMT-ko-KR-SMTl01:Korean}}"#,
         ))
     }
 
-    fn get_fields(&'a self) -> HashMap<&'a str, &'a str> {
-        let mut field_values: HashMap<&'a str, &'a str> = HashMap::with_capacity(3);
+    fn to_update_note_model(
+        &'a self,
+        note_id: &'a NoteId,
+        model_name: &'a str,
+        tags: Vec<&'a str>,
+    ) -> UpdateNoteModel<'a> {
+        UpdateNoteModel::new(UpdateNoteModelNote::new(
+            note_id,
+            model_name,
+            self.get_fields(),
+            tags,
+        ))
+    }
+
+    fn get_fields(&self) -> HashMap<&str, &str> {
+        let mut field_values: HashMap<&str, &str> = HashMap::with_capacity(FIELD_NAMES.len());
         field_values.insert("MarkdownID", self.markdown_id.as_str());
         field_values.insert("Front", self.front.as_str());
         field_values.insert("Back", self.back.as_str());
+        field_values.insert("Tts", self.tts.as_str());
+        for direction in CardDirection::all() {
+            let skip = if self.skipped.contains(&direction) {
+                "1"
+            } else {
+                ""
+            };
+            field_values.insert(direction.skip_field(), skip);
+        }
         field_values
     }
 
     fn to_add_note(&'a self, deck_name: &'a str, model_name: &'a str) -> AddNoteNote<'a> {
-        let mut fields: HashMap<&str, &str> = HashMap::with_capacity(3);
-        fields.insert("MarkdownID", self.markdown_id.as_str());
-        fields.insert("Front", self.front.as_str());
-        fields.insert("Back", self.back.as_str());
-
         AddNoteNote::new(
             deck_name,
             model_name,
-            fields,
+            self.get_fields(),
             AddNoteOptions::new(
                 false,
                 "deck",
@@ -197,6 +270,10 @@ TTS W: {{tts ko_KR voices=com.samsung.SMT-ko-KR-SMTl01:Korean}}"#,
         self.operation
     }
 
+    fn set_operation(&mut self, operation: NoteOperation) {
+        self.operation = operation;
+    }
+
     fn get_markdown_id(&'a self) -> &'a String {
         &self.markdown_id
     }
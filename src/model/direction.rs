@@ -0,0 +1,39 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+/// Direction in which a note is studied, each direction corresponds to one card template.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumIter, ValueEnum, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum CardDirection {
+    /// Shows the front and asks for the back.
+    Recognition,
+    /// Shows the back and asks for the front.
+    Recall,
+    /// Plays the audio and asks for the front and back.
+    Listen,
+}
+
+impl CardDirection {
+    pub fn all() -> Vec<CardDirection> {
+        CardDirection::iter().collect()
+    }
+
+    /// Name of the field that prevents the card from being generated for a note when it is not
+    /// empty. This allows choosing the directions per note while sharing the same model.
+    pub fn skip_field(&self) -> &'static str {
+        match self {
+            CardDirection::Recognition => "SkipRecognition",
+            CardDirection::Recall => "SkipRecall",
+            CardDirection::Listen => "SkipListen",
+        }
+    }
+
+    /// Wraps the front of a card template, so that the card is only generated when the skip field
+    /// of the note is empty.
+    pub fn wrap_front(&self, front: &str) -> String {
+        format!("{{{{^{0}}}}}\n{1}\n{{{{/{0}}}}}", self.skip_field(), front)
+    }
+}
@@ -1,8 +1,10 @@
 mod basic;
+mod direction;
 // mod rule;
+mod tts;
 // mod word;
 
-use crate::anki_connect::models_client::params::CreateModel;
+use crate::anki_connect::models_client::params::{CreateModel, UpdateModelTemplates};
 use crate::anki_connect::note::NoteId;
 use crate::anki_connect::notes_client::params as notes_params;
 use crate::anki_connect::notes_client::params::AddNoteNote;
@@ -11,6 +13,8 @@ use crate::note_operation::NoteOperation;
 
 use basic::Basic;
 // use rule::Rule;
+pub use direction::CardDirection;
+pub use tts::TtsOptions;
 // use word::Word;
 
 use derive_new::new;
@@ -42,19 +46,66 @@ pub trait InternalModelMethods<'a>: Debug + Default {
         callout: &Callout,
         header_lang: Option<&str>,
         deck_name: &'a str,
+        directions: &[CardDirection],
+        tts: Option<&TtsOptions>,
     ) -> Self;
-    fn to_create_model(&self, model_name: &'a str, css: Option<&'a str>) -> CreateModel<'a>;
-    fn get_fields(&'a self) -> HashMap<&'a str, &'a str>;
+    fn to_create_model(
+        &self,
+        model_name: &'a str,
+        css: Option<&'a str>,
+        options: &ModelOptions,
+    ) -> CreateModel<'a>;
+    fn to_update_templates(
+        &self,
+        model_name: &'a str,
+        options: &ModelOptions,
+    ) -> UpdateModelTemplates<'a>;
+    fn get_card_templates(&self, options: &ModelOptions) -> Vec<CardTemplate>;
+    fn get_field_names(&self) -> &'static [&'static str];
+    fn get_fields(&self) -> HashMap<&str, &str>;
     fn to_add_note(&'a self, deck_name: &'a str, model_name: &'a str) -> AddNoteNote<'a>;
     fn to_update_note(&'a self, note_id: &'a NoteId) -> notes_params::UpdateNoteFields<'a>;
+    fn to_update_note_model(
+        &'a self,
+        note_id: &'a NoteId,
+        model_name: &'a str,
+        tags: Vec<&'a str>,
+    ) -> notes_params::UpdateNoteModel<'a>;
     fn get_deck_name(&'a self) -> &'a str;
     fn get_operation(&'a self) -> NoteOperation;
+    fn set_operation(&mut self, operation: NoteOperation);
     fn get_markdown_id(&'a self) -> &'a String;
     fn get_audio(&'a self) -> Option<&'a Vec<MediaFile<'a>>>;
     fn get_video(&'a self) -> Option<&'a Vec<MediaFile<'a>>>;
     fn get_picture(&'a self) -> Option<&'a Vec<MediaFile<'a>>>;
 }
 
+/// Configuration of the card templates generated for a model.
+#[derive(Debug, Clone)]
+pub struct ModelOptions {
+    /// Text to speech settings, TTS is omitted from the templates if `None`.
+    pub tts: Option<TtsOptions>,
+    /// Directions for which card templates are created.
+    pub directions: Vec<CardDirection>,
+}
+
+impl Default for ModelOptions {
+    fn default() -> Self {
+        ModelOptions {
+            tts: None,
+            directions: CardDirection::all(),
+        }
+    }
+}
+
+/// Front and back templates of the card generated for a direction.
+#[derive(Debug, Clone, new)]
+pub struct CardTemplate {
+    pub direction: CardDirection,
+    pub front: String,
+    pub back: String,
+}
+
 #[derive(Debug, Serialize, Clone, new)]
 #[serde(rename_all = "camelCase")]
 pub struct MediaFile<'a> {
@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+/// Options used to generate Anki's `{{tts ...}}` template tags.
+///
+/// See <https://docs.ankiweb.net/templates/fields.html#text-to-speech-for-individual-fields>
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TtsOptions {
+    /// Locale of the text, e.g. `ko_KR`, `zh_CN` or `ja_JP`.
+    pub lang: String,
+    /// Voices in order of preference, the first one available on the device will be used.
+    pub voices: Vec<String>,
+    /// Playback speed, 1.0 is the normal speed.
+    pub speed: Option<f32>,
+    /// Names of the fields that should be spoken.
+    pub fields: Vec<String>,
+}
+
+impl Default for TtsOptions {
+    fn default() -> Self {
+        TtsOptions {
+            lang: String::new(),
+            voices: Vec::new(),
+            speed: None,
+            fields: vec!["Front".to_string()],
+        }
+    }
+}
+
+impl TtsOptions {
+    /// Checks that the options produce a tag that Anki can parse.
+    pub fn validate(&self, model_fields: &[&str]) -> Result<(), String> {
+        let is_invalid = |value: &str| {
+            value.is_empty()
+                || value
+                    .chars()
+                    .any(|c| c.is_whitespace() || matches!(c, ':' | ',' | '{' | '}'))
+        };
+        if is_invalid(&self.lang) {
+            return Err(format!("invalid TTS language: '{}'", self.lang));
+        }
+        if let Some(voice) = self.voices.iter().find(|voice| is_invalid(voice)) {
+            return Err(format!("invalid TTS voice: '{}'", voice));
+        }
+        if self.speed.is_some_and(|speed| speed <= 0.0) {
+            return Err(format!(
+                "invalid TTS speed: '{}'",
+                self.speed.unwrap_or_default()
+            ));
+        }
+        if self.fields.is_empty() {
+            return Err("at least one field must be spoken by TTS".to_string());
+        }
+        if let Some(field) = self
+            .fields
+            .iter()
+            .find(|field| !model_fields.contains(&field.as_str()))
+        {
+            return Err(format!(
+                "TTS field '{}' does not exist in the model, expected one of: {}",
+                field,
+                model_fields.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    /// Builds the tag that reads out a single field, e.g.
+    /// `{{tts ko_KR voices=Apple_Yuna,Microsoft_Heami:Front}}`.
+    pub fn to_tag(&self, field: &str) -> String {
+        let mut tag = format!("{{{{tts {}", self.lang);
+        if !self.voices.is_empty() {
+            tag.push_str(&format!(" voices={}", self.voices.join(",")));
+        }
+        if let Some(speed) = self.speed {
+            tag.push_str(&format!(" speed={}", speed));
+        }
+        tag.push_str(&format!(":{}}}}}", field));
+        tag
+    }
+
+    /// Builds the tag that Anki renders for [`TtsOptions::to_tag`], reading out the given text. It
+    /// can be written into a field, so that a note is read out with its own options.
+    pub fn to_av_tag(&self, text: &str) -> String {
+        let mut tag = format!("[anki:tts lang={}", self.lang);
+        if !self.voices.is_empty() {
+            tag.push_str(&format!(" voices={}", self.voices.join(",")));
+        }
+        if let Some(speed) = self.speed {
+            tag.push_str(&format!(" speed={}", speed));
+        }
+        tag.push_str(&format!("]{}[/anki:tts]", text));
+        tag
+    }
+
+    /// Builds the template markup reading out every configured field.
+    pub fn to_template(&self) -> String {
+        self.fields
+            .iter()
+            .map(|field| format!(r#"<div class="center">{}</div>"#, self.to_tag(field)))
+            .collect::<Vec<_>>()
+            .join("\n<br>\n")
+    }
+}
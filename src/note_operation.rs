@@ -5,6 +5,12 @@ pub enum NoteOperation {
     Add,
     Update,
     Move,
+    /// The note exists in Anki and its fields are already up to date.
+    Unchanged,
+    /// The note exists in Anki, but uses a different model than the one being synced.
+    ChangeModel,
+    /// The note was edited in Anki since the last sync and is left untouched.
+    Conflict,
     // Delete,
     Nop,
 }
@@ -32,7 +32,7 @@ pub struct Step {
 
 impl Step {
     pub fn print_step(&mut self, message: Option<&str>, emoji: Option<Emoji<'_, '_>>) {
-        println!(
+        eprintln!(
             "{0} {1}{2}",
             style(format!("[{}/{}]", self.step_num, self.step_max))
                 .bold()
@@ -50,7 +50,7 @@ pub fn print_step(
     message: Option<&str>,
     emoji: Option<Emoji<'_, '_>>,
 ) {
-    println!(
+    eprintln!(
         "{0} {1}{2}",
         style(format!("[{}/{}]", step_num, step_max)).bold().dim(),
         emoji.unwrap_or(Emoji("", "")),
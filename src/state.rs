@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::fs::{File, create_dir_all, read_to_string};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+use crate::anki_connect::{note::NoteId, notes_client::responses::NoteInfo};
+use crate::error::M2AnkiError;
+
+/// Name of the directory, inside of the input directory, where md2anki keeps its state.
+pub const STATE_DIR_NAME: &str = ".md2anki";
+const STATE_FILE_NAME: &str = "state.json";
+
+/// What to do with a note that was edited in Anki since it was last written by a sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Display, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Overwrite the note with the content of the markdown file
+    Markdown,
+    /// Keep the note as it was edited in Anki
+    Anki,
+    /// Leave the note untouched and report it
+    #[default]
+    Skip,
+}
+
+/// Information remembered about the notes written to Anki by the previous syncs, so that the
+/// files and notes that didn't change can be skipped.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncState {
+    /// Hash of the settings used by the last sync, every note is synced again when they change.
+    pub settings: String,
+    /// Notes indexed by their markdown ID.
+    pub notes: HashMap<String, NoteState>,
+    /// Hash of the content of every markdown file that was fully synced.
+    pub files: HashMap<PathBuf, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteState {
+    pub note_id: NoteId,
+    /// Modification time of the note in Anki right after it was last synced.
+    pub mtime: u64,
+    /// Hash of the callout and of the settings it was synced with.
+    #[serde(default)]
+    pub hash: String,
+    /// Deck to which the note was synced.
+    #[serde(default)]
+    pub deck: String,
+    /// Markdown file containing the callout of the note.
+    #[serde(default)]
+    pub file: PathBuf,
+}
+
+/// FNV-1a hasher, unlike `DefaultHasher` its output is stable, so it can be stored in the state.
+#[derive(Debug)]
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        StableHasher(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+/// Hashes a value into the hexadecimal string stored in the state.
+pub fn stable_hash<T: Hash + ?Sized>(value: &T) -> String {
+    let mut hasher = StableHasher::default();
+    value.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+impl SyncState {
+    pub fn path(input_dir: &Path) -> PathBuf {
+        input_dir.join(STATE_DIR_NAME).join(STATE_FILE_NAME)
+    }
+
+    /// Loads the state of the input directory, which is empty before the first sync.
+    pub fn load(input_dir: &Path) -> Result<Self, M2AnkiError> {
+        let path = Self::path(input_dir);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_str(&read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, input_dir: &Path) -> Result<(), M2AnkiError> {
+        let path = Self::path(input_dir);
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir)?;
+        }
+        let mut f = File::create(path)?;
+        f.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Checks whether the note was synced from the same callout, deck and settings.
+    pub fn is_note_unchanged(&self, markdown_id: &str, hash: &str) -> bool {
+        self.notes
+            .get(markdown_id)
+            .is_some_and(|state| state.hash == hash)
+    }
+
+    /// Checks whether the note was modified in Anki after it was last synced.
+    pub fn is_conflicted(&self, note: &NoteInfo) -> bool {
+        self.notes
+            .get(&note.markdown_id)
+            .is_some_and(|state| note.mtime > state.mtime)
+    }
+}
@@ -0,0 +1,93 @@
+use strum::IntoEnumIterator;
+
+use crate::callout::callout_type::CalloutType;
+
+/// Stylesheet used for the cards, user stylesheets are layered on top of it.
+pub const DEFAULT_CSS: &str = include_str!("../cards_style.css");
+
+/// Returns the inner SVG markup of the Lucide icons used by the callouts.
+fn icon_svg(name: &str) -> Option<&'static str> {
+    match name {
+        "alert-triangle" => Some(
+            r#"<path d="m20.73 18-8-14a2 2 0 0 0-3.48 0l-8 14A2 2 0 0 0 4 21h16a2 2 0 0 0 1.73-3"></path><path d="M12 9v4"></path><path d="M12 17h.01"></path>"#,
+        ),
+        "bug" => Some(
+            r#"<path d="m8 2 1.88 1.88"></path><path d="M14.12 3.88 16 2"></path><path d="M9 7.13v-1a3.003 3.003 0 1 1 6 0v1"></path><path d="M12 20c-3.3 0-6-2.7-6-6v-3a4 4 0 0 1 4-4h4a4 4 0 0 1 4 4v3c0 3.3-2.7 6-6 6"></path><path d="M12 20v-9"></path><path d="M6.53 9C4.6 8.8 3 7.1 3 5"></path><path d="M6 13H2"></path><path d="M3 21c0-2.1 1.7-3.9 3.8-4"></path><path d="M20.97 5c0 2.1-1.6 3.8-3.5 4"></path><path d="M22 13h-4"></path><path d="M17.2 17c2.1.1 3.8 1.9 3.8 4"></path>"#,
+        ),
+        "check" => Some(r#"<path d="M20 6 9 17l-5-5"></path>"#),
+        "check-circle-2" => {
+            Some(r#"<circle cx="12" cy="12" r="10"></circle><path d="m9 12 2 2 4-4"></path>"#)
+        }
+        "clipboard-list" => Some(
+            r#"<rect x="8" y="2" width="8" height="4" rx="1" ry="1"></rect><path d="M16 4h2a2 2 0 0 1 2 2v14a2 2 0 0 1-2 2H6a2 2 0 0 1-2-2V6a2 2 0 0 1 2-2h2"></path><path d="M12 11h4"></path><path d="M12 16h4"></path><path d="M8 11h.01"></path><path d="M8 16h.01"></path>"#,
+        ),
+        "flame" => Some(
+            r#"<path d="M8.5 14.5A2.5 2.5 0 0 0 11 12c0-1.38-.5-2-1-3-1.072-2.143-.224-4.054 2-6 .5 2.5 2 4.9 4 6.5 2 1.6 3 3.5 3 5.5a7 7 0 1 1-14 0c0-1.153.433-2.294 1-3a2.5 2.5 0 0 0 2.5 2.5z"></path>"#,
+        ),
+        "help-circle" => Some(
+            r#"<circle cx="12" cy="12" r="10"></circle><path d="M9.09 9a3 3 0 0 1 5.83 1c0 2-3 3-3 3"></path><path d="M12 17h.01"></path>"#,
+        ),
+        "info" => Some(
+            r#"<circle cx="12" cy="12" r="10"></circle><path d="M12 16v-4"></path><path d="M12 8h.01"></path>"#,
+        ),
+        "link" => Some(
+            r#"<path d="M10 13a5 5 0 0 0 7.54.54l3-3a5 5 0 0 0-7.07-7.07l-1.72 1.71"></path><path d="M14 11a5 5 0 0 0-7.54-.54l-3 3a5 5 0 0 0 7.07 7.07l1.71-1.71"></path>"#,
+        ),
+        "list" => Some(
+            r#"<line x1="8" y1="6" x2="21" y2="6"></line><line x1="8" y1="12" x2="21" y2="12"></line><line x1="8" y1="18" x2="21" y2="18"></line><line x1="3" y1="6" x2="3.01" y2="6"></line><line x1="3" y1="12" x2="3.01" y2="12"></line><line x1="3" y1="18" x2="3.01" y2="18"></line>"#,
+        ),
+        "pencil" => Some(
+            r#"<path d="M21.174 6.812a1 1 0 0 0-3.986-3.987L3.842 16.174a2 2 0 0 0-.5.83l-1.321 4.352a.5.5 0 0 0 .623.622l4.353-1.32a2 2 0 0 0 .83-.497z"></path><path d="m15 5 4 4"></path>"#,
+        ),
+        "quote" => Some(
+            r#"<path d="M16 3a2 2 0 0 0-2 2v6a2 2 0 0 0 2 2 1 1 0 0 1 1 1v1a2 2 0 0 1-2 2 1 1 0 0 0-1 1v2a1 1 0 0 0 1 1 6 6 0 0 0 6-6V5a2 2 0 0 0-2-2z"></path><path d="M5 3a2 2 0 0 0-2 2v6a2 2 0 0 0 2 2 1 1 0 0 1 1 1v1a2 2 0 0 1-2 2 1 1 0 0 0-1 1v2a1 1 0 0 0 1 1 6 6 0 0 0 6-6V5a2 2 0 0 0-2-2z"></path>"#,
+        ),
+        "scroll-text" => Some(
+            r#"<path d="M15 12h-5"></path><path d="M15 8h-5"></path><path d="M19 17V5a2 2 0 0 0-2-2H4"></path><path d="M8 21h12a2 2 0 0 0 2-2v-1a1 1 0 0 0-1-1H11a1 1 0 0 0-1 1v1a2 2 0 1 1-4 0V5a2 2 0 1 0-4 0v2a1 1 0 0 0 1 1h3"></path>"#,
+        ),
+        "square-function" => Some(
+            r#"<rect width="18" height="18" x="3" y="3" rx="2" ry="2"></rect><path d="M9 17c2 0 2.8-1 2.8-2.8V10c0-2 1-3.3 3.2-3"></path><path d="M9 11.2h5.7"></path>"#,
+        ),
+        "whole-word" => Some(
+            r#"<circle cx="7" cy="12" r="3"></circle><path d="M10 9v6"></path><circle cx="17" cy="12" r="3"></circle><path d="M14 7v8"></path><path d="M22 17v1c0 .5-.5 1-1 1H3c-.5 0-1-.5-1-1v-1"></path>"#,
+        ),
+        "x" => Some(r#"<path d="M18 6 6 18"></path><path d="m6 6 12 12"></path>"#),
+        "zap" => Some(
+            r#"<path d="M4 14a1 1 0 0 1-.78-1.63l9.9-10.2a.5.5 0 0 1 .86.46l-1.92 6.02A1 1 0 0 0 13 10h7a1 1 0 0 1 .78 1.63l-9.9 10.2a.5.5 0 0 1-.86-.46l1.92-6.02A1 1 0 0 0 11 14z"></path>"#,
+        ),
+        _ => None,
+    }
+}
+
+/// Generates the rules setting the icon of every callout type, the icons are embedded as data
+/// URLs so that cards don't depend on media files.
+pub fn icons_css() -> String {
+    CalloutType::iter()
+        .filter_map(|callout_type| {
+            let svg = icon_svg(callout_type.icon())?;
+            Some(format!(
+                r#".callout[data-callout="{}"] {{
+    --callout-icon: url('data:image/svg+xml;charset=UTF-8,<svg xmlns="http://www.w3.org/2000/svg" width="24" height="24" viewBox="0 0 24 24" fill="none" stroke="black" stroke-width="2" stroke-linecap="round" stroke-linejoin="round" class="svg-icon lucide-{}">{}</svg>');
+}}"#,
+                callout_type,
+                callout_type.icon(),
+                svg
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Builds the stylesheet of the model, the user stylesheet is appended to the default one so that
+/// its rules take precedence, unless the default stylesheet is disabled.
+pub fn build_css(user_css: Option<&str>, use_default: bool) -> String {
+    let mut parts: Vec<String> = Vec::with_capacity(3);
+    if use_default {
+        parts.push(DEFAULT_CSS.to_string());
+        parts.push(format!("/* Callout icons */\n{}", icons_css()));
+    }
+    if let Some(css) = user_css.filter(|css| !css.trim().is_empty()) {
+        parts.push(format!("/* User styles */\n{}", css));
+    }
+    parts.join("\n\n")
+}
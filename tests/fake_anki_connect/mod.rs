@@ -0,0 +1,1231 @@
+//! In-memory fake of AnkiConnect, served over a local HTTP listener.
+//!
+//! It keeps a collection of decks, models, notes and cards, and implements the actions used by
+//! md2anki with the same results and error messages as AnkiConnect. Every card template
+//! generates a card, whether its front side would be empty or not.
+#![allow(dead_code)]
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::{Map, Value, json};
+
+const DEFAULT_DECK: &str = "Default";
+
+/// Actions implemented by the fake, as listed by `apiReflect`.
+const ACTIONS: &[&str] = &[
+    "version",
+    "apiReflect",
+    "multi",
+    "deckNames",
+    "deckNamesAndIds",
+    "createDeck",
+    "deleteDecks",
+    "changeDeck",
+    "getDecks",
+    "modelNames",
+    "findModelsByName",
+    "createModel",
+    "updateModelStyling",
+    "updateModelTemplates",
+    "modelFieldAdd",
+    "modelTemplateAdd",
+    "modelTemplateRemove",
+    "findNotes",
+    "findCards",
+    "notesInfo",
+    "addNote",
+    "addNotes",
+    "updateNoteFields",
+    "updateNote",
+    "updateNoteModel",
+    "deleteNotes",
+    "suspend",
+    "unsuspend",
+    "areSuspended",
+    "cardsInfo",
+    "cardsToNotes",
+    "setDueDate",
+    "forgetCards",
+    "answerCards",
+    "storeMediaFile",
+    "retrieveMediaFile",
+    "getMediaFilesNames",
+    "getMediaDirPath",
+    "deleteMediaFile",
+    "guiBrowse",
+    "guiEditNote",
+    "getProfiles",
+    "getActiveProfile",
+    "loadProfile",
+    "sync",
+];
+
+#[derive(Debug, Clone)]
+pub struct FakeModel {
+    pub id: i64,
+    pub name: String,
+    pub fields: Vec<String>,
+    /// Name, front and back of each template, in order.
+    pub templates: Vec<(String, String, String)>,
+    pub css: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct FakeNote {
+    pub id: i64,
+    pub model: String,
+    /// Values of the fields, in the order of the fields of the model.
+    pub fields: Vec<(String, String)>,
+    pub tags: Vec<String>,
+    pub mtime: i64,
+}
+
+impl FakeNote {
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FakeCard {
+    pub id: i64,
+    pub note: i64,
+    pub deck: String,
+    pub ord: usize,
+    pub suspended: bool,
+    /// Whether the card was reviewed, new cards are not.
+    pub is_review: bool,
+    /// Day on which a review card is due, today is day 0.
+    pub due: i64,
+    pub interval: i64,
+    pub factor: u32,
+    pub reps: u32,
+    pub lapses: u32,
+}
+
+/// Content of the fake Anki collection.
+#[derive(Debug)]
+pub struct Collection {
+    next_id: i64,
+    mtime: i64,
+    pub decks: BTreeMap<String, i64>,
+    pub models: BTreeMap<String, FakeModel>,
+    pub notes: BTreeMap<i64, FakeNote>,
+    pub cards: BTreeMap<i64, FakeCard>,
+    /// Base64 encoded content of the media files, by name.
+    pub media: BTreeMap<String, String>,
+    /// Actions answered as unsupported, like by an older version of the add-on.
+    pub removed_actions: BTreeSet<String>,
+    /// Actions that are supported but fail, like when the collection is busy.
+    pub failing_actions: BTreeSet<String>,
+    /// Notes that `addNote` fails to add, by the end of their first field.
+    pub rejected_notes: BTreeSet<String>,
+    /// Last search shown in the card browser.
+    pub browser_query: Option<String>,
+    /// Last note opened in the editor.
+    pub edited_note: Option<i64>,
+    pub profiles: Vec<String>,
+    /// Profile that is open, the collection is shared by every profile.
+    pub profile: String,
+    /// Number of times the collection was synced with AnkiWeb.
+    pub num_ankiweb_syncs: usize,
+}
+
+impl Default for Collection {
+    fn default() -> Self {
+        Collection {
+            next_id: 1_700_000_000_000,
+            mtime: 1_700_000_000,
+            decks: BTreeMap::from([(DEFAULT_DECK.to_string(), 1)]),
+            models: BTreeMap::new(),
+            notes: BTreeMap::new(),
+            cards: BTreeMap::new(),
+            media: BTreeMap::new(),
+            removed_actions: BTreeSet::new(),
+            failing_actions: BTreeSet::new(),
+            rejected_notes: BTreeSet::new(),
+            browser_query: None,
+            edited_note: None,
+            profiles: vec!["User 1".to_string(), "User 2".to_string()],
+            profile: "User 1".to_string(),
+            num_ankiweb_syncs: 0,
+        }
+    }
+}
+
+type ActionResult = Result<Value, String>;
+
+fn param<'a>(params: &'a Value, name: &str) -> Result<&'a Value, String> {
+    params
+        .get(name)
+        .ok_or_else(|| format!("missing parameter: {}", name))
+}
+
+fn str_param<'a>(params: &'a Value, name: &str) -> Result<&'a str, String> {
+    param(params, name)?
+        .as_str()
+        .ok_or_else(|| format!("parameter is not a string: {}", name))
+}
+
+fn ids_param(params: &Value, name: &str) -> Result<Vec<i64>, String> {
+    param(params, name)?
+        .as_array()
+        .ok_or_else(|| format!("parameter is not an array: {}", name))?
+        .iter()
+        .map(|id| {
+            id.as_i64()
+                .ok_or_else(|| format!("invalid id in parameter: {}", name))
+        })
+        .collect()
+}
+
+fn strings(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|value| value.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Whether the name matches a media pattern, where `*` matches any characters, `?` a single one
+/// and `[*]` a literal character.
+fn matches_pattern(name: &[char], pattern: &[char]) -> bool {
+    match pattern {
+        [] => name.is_empty(),
+        ['*', rest @ ..] => (0..=name.len()).any(|skip| matches_pattern(&name[skip..], rest)),
+        ['?', rest @ ..] => !name.is_empty() && matches_pattern(&name[1..], rest),
+        ['[', c, ']', rest @ ..] => name.first() == Some(c) && matches_pattern(&name[1..], rest),
+        [c, rest @ ..] => name.first() == Some(c) && matches_pattern(&name[1..], rest),
+    }
+}
+
+/// Whether the text matches a search term of Anki, where `*` matches any characters, `_` a single
+/// one and a backslash escapes the next character.
+fn matches_search(text: &[char], search: &[char]) -> bool {
+    match search {
+        [] => text.is_empty(),
+        ['\\', c, rest @ ..] => text.first() == Some(c) && matches_search(&text[1..], rest),
+        ['*', rest @ ..] => (0..=text.len()).any(|skip| matches_search(&text[skip..], rest)),
+        ['_', rest @ ..] => !text.is_empty() && matches_search(&text[1..], rest),
+        [c, rest @ ..] => text.first() == Some(c) && matches_search(&text[1..], rest),
+    }
+}
+
+/// Whether the deck, or one of its parents, matches a `deck:` search.
+fn is_in_searched_deck(deck: &str, search: &str) -> bool {
+    let search: Vec<char> = search.chars().collect();
+    let mut end = deck.len();
+    loop {
+        if matches_search(&deck[..end].chars().collect::<Vec<_>>(), &search) {
+            return true;
+        }
+        match deck[..end].rfind("::") {
+            Some(index) => end = index,
+            None => return false,
+        }
+    }
+}
+
+/// Whether the deck is the given deck or one of its subdecks.
+fn is_in_deck(deck: &str, parent: &str) -> bool {
+    deck == parent
+        || deck
+            .strip_prefix(parent)
+            .is_some_and(|rest| rest.starts_with("::"))
+}
+
+impl Collection {
+    fn next_id(&mut self) -> i64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn tick(&mut self) -> i64 {
+        self.mtime += 1;
+        self.mtime
+    }
+
+    /// Creates the deck and its parents, returns the id of the deck.
+    pub fn create_deck(&mut self, name: &str) -> i64 {
+        let mut end = 0;
+        for part in name.split("::") {
+            end += part.len();
+            if !self.decks.contains_key(&name[..end]) {
+                let id = self.next_id();
+                self.decks.insert(name[..end].to_string(), id);
+            }
+            end += 2;
+        }
+        self.decks[name]
+    }
+
+    /// Simulates an edit of the note made in Anki.
+    pub fn edit_note(&mut self, note_id: i64, field: &str, value: &str) {
+        let mtime = self.tick();
+        let note = self.notes.get_mut(&note_id).expect("note exists");
+        for (name, field_value) in note.fields.iter_mut() {
+            if name == field {
+                *field_value = value.to_string();
+            }
+        }
+        note.mtime = mtime;
+    }
+
+    pub fn note_cards(&self, note_id: i64) -> Vec<&FakeCard> {
+        self.cards
+            .values()
+            .filter(|card| card.note == note_id)
+            .collect()
+    }
+
+    pub fn notes_in_deck(&self, deck: &str) -> Vec<&FakeNote> {
+        let ids: BTreeSet<i64> = self
+            .cards
+            .values()
+            .filter(|card| is_in_deck(&card.deck, deck))
+            .map(|card| card.note)
+            .collect();
+        ids.iter().map(|id| &self.notes[id]).collect()
+    }
+
+    /// Finds the cards matching a search query. Supports `deck:`, `nid:` and `MarkdownID:`
+    /// searches with wildcards, combined with `OR`.
+    fn find_cards(&self, query: &str) -> Result<Vec<i64>, String> {
+        let mut cards = BTreeSet::new();
+        for term in query.split(" OR ") {
+            let term = term.trim().trim_matches('"');
+            let matches: Box<dyn Fn(&FakeCard) -> bool> =
+                if let Some(deck) = term.strip_prefix("deck:") {
+                    // Like in Anki, an unquoted deck name ends at the first space and the rest of
+                    // the term searches the fields of the notes
+                    let (deck, text) = match deck.strip_prefix('"') {
+                        Some(deck) => (deck.trim_end_matches('"'), None),
+                        None => match deck.split_once(' ') {
+                            Some((deck, text)) => (deck, Some(text.to_string())),
+                            None => (deck, None),
+                        },
+                    };
+                    let deck = deck.to_string();
+                    Box::new(move |card| {
+                        is_in_searched_deck(&card.deck, &deck)
+                            && text.as_ref().is_none_or(|text| {
+                                self.notes[&card.note]
+                                    .fields
+                                    .iter()
+                                    .any(|(_, value)| value.contains(text.as_str()))
+                            })
+                    })
+                } else if let Some(ids) = term.strip_prefix("nid:") {
+                    let ids: Vec<i64> = ids.split(',').filter_map(|id| id.parse().ok()).collect();
+                    Box::new(move |card| ids.contains(&card.note))
+                } else if let Some(id) = term.strip_prefix("MarkdownID:") {
+                    let id: Vec<char> = id.chars().collect();
+                    Box::new(move |card| {
+                        self.notes[&card.note]
+                            .field("MarkdownID")
+                            .is_some_and(|value| {
+                                matches_search(&value.chars().collect::<Vec<_>>(), &id)
+                            })
+                    })
+                } else {
+                    return Err(format!("unsupported query: {}", query));
+                };
+            cards.extend(
+                self.cards
+                    .values()
+                    .filter(|card| matches(card))
+                    .map(|card| card.id),
+            );
+        }
+        Ok(cards.into_iter().collect())
+    }
+
+    fn find_notes(&self, query: &str) -> Result<Vec<i64>, String> {
+        let notes: BTreeSet<i64> = self
+            .find_cards(query)?
+            .iter()
+            .map(|card| self.cards[card].note)
+            .collect();
+        Ok(notes.into_iter().collect())
+    }
+
+    fn model(&self, name: &str) -> Result<&FakeModel, String> {
+        self.models
+            .get(name)
+            .ok_or_else(|| format!("model was not found: {}", name))
+    }
+
+    fn model_info(&self, model: &FakeModel) -> Value {
+        json!({
+            "id": model.id,
+            "name": model.name,
+            "type": 0,
+            "mod": self.mtime,
+            "usn": -1,
+            "sortf": 0,
+            "did": null,
+            "tmpls": model.templates.iter().enumerate().map(|(ord, (name, front, back))| json!({
+                "name": name,
+                "ord": ord,
+                "qfmt": front,
+                "afmt": back,
+                "did": null,
+                "bqfmt": "",
+                "bafmt": "",
+            })).collect::<Vec<_>>(),
+            "flds": model.fields.iter().enumerate().map(|(ord, name)| json!({
+                "name": name,
+                "ord": ord,
+                "sticky": false,
+                "rtl": false,
+                "font": "Arial",
+                "size": 20,
+                "media": [],
+            })).collect::<Vec<_>>(),
+            "css": model.css,
+            "req": [],
+        })
+    }
+
+    fn note_info(&self, note: &FakeNote) -> Value {
+        let fields: Map<String, Value> = note
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(order, (name, value))| (name.clone(), json!({"value": value, "order": order})))
+            .collect();
+        json!({
+            "noteId": note.id,
+            "profile": self.profile,
+            "modelName": note.model,
+            "tags": note.tags,
+            "fields": fields,
+            "mod": note.mtime,
+            "cards": self.note_cards(note.id).iter().map(|card| card.id).collect::<Vec<_>>(),
+        })
+    }
+
+    fn add_card(&mut self, note: i64, deck: &str, ord: usize) {
+        let id = self.next_id();
+        self.cards.insert(
+            id,
+            FakeCard {
+                id,
+                note,
+                deck: deck.to_string(),
+                ord,
+                suspended: false,
+                is_review: false,
+                due: id,
+                interval: 0,
+                factor: 0,
+                reps: 0,
+                lapses: 0,
+            },
+        );
+    }
+
+    fn remove_cards(&mut self, cards: &BTreeSet<i64>) {
+        self.cards.retain(|id, _| !cards.contains(id));
+        let notes_with_cards: BTreeSet<i64> = self.cards.values().map(|card| card.note).collect();
+        self.notes.retain(|id, _| notes_with_cards.contains(id));
+    }
+
+    fn add_note(&mut self, note: &Value) -> ActionResult {
+        let deck = str_param(note, "deckName")?;
+        let model = self.model(str_param(note, "modelName")?)?.clone();
+        if !self.decks.contains_key(deck) {
+            return Err(format!("deck was not found: {}", deck));
+        }
+        let values = param(note, "fields")?;
+        let fields: Vec<(String, String)> = model
+            .fields
+            .iter()
+            .map(|name| {
+                let value = values.get(name).and_then(Value::as_str).unwrap_or_default();
+                (name.clone(), value.to_string())
+            })
+            .collect();
+        let first_field = &fields[0].1;
+        if first_field.is_empty() {
+            return Err("cannot create note because it is empty".to_string());
+        }
+        if self
+            .rejected_notes
+            .iter()
+            .any(|end| first_field.ends_with(end))
+        {
+            return Err("cannot create note: rejected".to_string());
+        }
+        let options = note.get("options").cloned().unwrap_or_default();
+        let allow_duplicate = options
+            .get("allowDuplicate")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let duplicate_deck =
+            (options.get("duplicateScope").and_then(Value::as_str) == Some("deck")).then(|| {
+                options
+                    .pointer("/duplicateScopeOptions/deckName")
+                    .and_then(Value::as_str)
+                    .unwrap_or(deck)
+            });
+        let is_duplicate = self.notes.values().any(|other| {
+            other.model == model.name
+                && other.fields[0].1 == *first_field
+                && duplicate_deck.is_none_or(|deck| {
+                    self.note_cards(other.id)
+                        .iter()
+                        .any(|card| card.deck == deck)
+                })
+        });
+        if is_duplicate && !allow_duplicate {
+            return Err("cannot create note because it is a duplicate".to_string());
+        }
+
+        let id = self.next_id();
+        let mtime = self.tick();
+        self.notes.insert(
+            id,
+            FakeNote {
+                id,
+                model: model.name.clone(),
+                fields,
+                tags: strings(note.get("tags")),
+                mtime,
+            },
+        );
+        for ord in 0..model.templates.len() {
+            self.add_card(id, deck, ord);
+        }
+        Ok(json!(id))
+    }
+
+    fn update_note(&mut self, note: &Value, fields: bool, tags: bool) -> ActionResult {
+        let id = param(note, "id")?
+            .as_i64()
+            .ok_or("note id is not a number")?;
+        let mtime = self.tick();
+        let stored = self
+            .notes
+            .get_mut(&id)
+            .ok_or_else(|| format!("note was not found: {}", id))?;
+        if let (true, Some(values)) = (fields, note.get("fields")) {
+            for (name, value) in stored.fields.iter_mut() {
+                if let Some(new_value) = values.get(name.as_str()).and_then(Value::as_str) {
+                    *value = new_value.to_string();
+                }
+            }
+        }
+        if let (true, Some(new_tags)) = (tags, note.get("tags")) {
+            stored.tags = strings(Some(new_tags));
+        }
+        stored.mtime = mtime;
+        Ok(Value::Null)
+    }
+
+    fn update_note_model(&mut self, note: &Value) -> ActionResult {
+        let id = param(note, "id")?
+            .as_i64()
+            .ok_or("note id is not a number")?;
+        let model = self.model(str_param(note, "modelName")?)?.clone();
+        if !self.notes.contains_key(&id) {
+            return Err(format!("note was not found: {}", id));
+        }
+        let values = param(note, "fields")?;
+        let mtime = self.tick();
+        let stored = self.notes.get_mut(&id).unwrap();
+        stored.model = model.name.clone();
+        stored.fields = model
+            .fields
+            .iter()
+            .map(|name| {
+                let value = values.get(name).and_then(Value::as_str).unwrap_or_default();
+                (name.clone(), value.to_string())
+            })
+            .collect();
+        stored.tags = strings(note.get("tags"));
+        stored.mtime = mtime;
+
+        // Cards are kept by position, the missing ones are added to the deck of the first card
+        let cards: Vec<FakeCard> = self.note_cards(id).into_iter().cloned().collect();
+        let deck = cards
+            .first()
+            .map_or(DEFAULT_DECK.to_string(), |card| card.deck.clone());
+        let removed: BTreeSet<i64> = cards
+            .iter()
+            .filter(|card| card.ord >= model.templates.len())
+            .map(|card| card.id)
+            .collect();
+        self.cards.retain(|card_id, _| !removed.contains(card_id));
+        for ord in cards.len()..model.templates.len() {
+            self.add_card(id, &deck, ord);
+        }
+        Ok(Value::Null)
+    }
+
+    fn create_model(&mut self, params: &Value) -> ActionResult {
+        let name = str_param(params, "modelName")?;
+        if self.models.contains_key(name) {
+            return Err(format!("Model name already exists: {}", name));
+        }
+        let template = |template: &Value, key: &str| {
+            template
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let templates = param(params, "cardTemplates")?
+            .as_array()
+            .ok_or("cardTemplates is not an array")?
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                let name = value
+                    .get("Name")
+                    .and_then(Value::as_str)
+                    .map_or(format!("Card {}", index + 1), str::to_string);
+                (name, template(value, "Front"), template(value, "Back"))
+            })
+            .collect();
+        let model = FakeModel {
+            id: self.next_id(),
+            name: name.to_string(),
+            fields: strings(params.get("inOrderFields")),
+            templates,
+            css: params
+                .get("css")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+        };
+        let info = self.model_info(&model);
+        self.models.insert(name.to_string(), model);
+        Ok(info)
+    }
+
+    fn model_template_add(&mut self, params: &Value) -> ActionResult {
+        let model_name = str_param(params, "modelName")?;
+        let template = param(params, "template")?;
+        let get = |key: &str| {
+            template
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let ord = self.model(model_name)?.templates.len();
+        let notes: Vec<i64> = self
+            .notes
+            .values()
+            .filter(|note| note.model == model_name)
+            .map(|note| note.id)
+            .collect();
+        self.models.get_mut(model_name).unwrap().templates.push((
+            get("Name"),
+            get("Front"),
+            get("Back"),
+        ));
+        for note in notes {
+            let deck = self.note_cards(note)[0].deck.clone();
+            self.add_card(note, &deck, ord);
+        }
+        Ok(Value::Null)
+    }
+
+    fn model_template_remove(&mut self, params: &Value) -> ActionResult {
+        let model_name = str_param(params, "modelName")?;
+        let template_name = str_param(params, "templateName")?;
+        let ord = self
+            .model(model_name)?
+            .templates
+            .iter()
+            .position(|(name, _, _)| name == template_name)
+            .ok_or_else(|| format!("template was not found: {}", template_name))?;
+        self.models
+            .get_mut(model_name)
+            .unwrap()
+            .templates
+            .remove(ord);
+        let notes: BTreeSet<i64> = self
+            .notes
+            .values()
+            .filter(|note| note.model == model_name)
+            .map(|note| note.id)
+            .collect();
+        self.cards
+            .retain(|_, card| !(notes.contains(&card.note) && card.ord == ord));
+        for card in self.cards.values_mut() {
+            if notes.contains(&card.note) && card.ord > ord {
+                card.ord -= 1;
+            }
+        }
+        Ok(Value::Null)
+    }
+
+    fn card_info(&self, card: &FakeCard) -> Value {
+        let note = &self.notes[&card.note];
+        let (kind, queue) = match (card.is_review, card.suspended) {
+            (false, false) => (0, 0),
+            (false, true) => (0, -1),
+            (true, false) => (2, 2),
+            (true, true) => (2, -1),
+        };
+        json!({
+            "cardId": card.id,
+            "note": card.note,
+            "deckName": card.deck,
+            "modelName": note.model,
+            "ord": card.ord,
+            "type": kind,
+            "queue": queue,
+            "due": card.due,
+            "interval": card.interval,
+            "factor": card.factor,
+            "reps": card.reps,
+            "lapses": card.lapses,
+            "left": 0,
+            "mod": note.mtime,
+            "question": note.fields.get(1).map_or("", |(_, value)| value),
+            "answer": note.fields.get(2).map_or("", |(_, value)| value),
+        })
+    }
+
+    fn card_mut(&mut self, id: i64) -> Result<&mut FakeCard, String> {
+        self.cards
+            .get_mut(&id)
+            .ok_or_else(|| format!("card was not found: {}", id))
+    }
+
+    /// Makes the cards review cards due in a number of days, the first one of a `3-7` range.
+    /// A `!` suffix also sets the interval to that number of days.
+    fn set_due_date(&mut self, params: &Value) -> ActionResult {
+        let days = str_param(params, "days")?;
+        let set_interval = days.ends_with('!');
+        let due: i64 = days
+            .trim_end_matches('!')
+            .split('-')
+            .next()
+            .and_then(|days| days.parse().ok())
+            .ok_or_else(|| format!("invalid days: {}", days))?;
+        for id in ids_param(params, "cards")? {
+            let card = self.card_mut(id)?;
+            card.is_review = true;
+            card.due = due;
+            if set_interval || card.interval == 0 {
+                card.interval = due.max(1);
+            }
+            if card.factor == 0 {
+                card.factor = 2500;
+            }
+        }
+        Ok(json!(true))
+    }
+
+    /// Answers the cards, the interval is kept by `Hard`, doubled by `Good` and tripled by
+    /// `Easy`, while `Again` resets it to a day.
+    fn answer_cards(&mut self, params: &Value) -> ActionResult {
+        let answers = param(params, "answers")?
+            .as_array()
+            .ok_or("answers is not an array")?;
+        let mut found = Vec::with_capacity(answers.len());
+        for answer in answers {
+            let id = param(answer, "cardId")?.as_i64().unwrap_or_default();
+            let ease = param(answer, "ease")?.as_i64().unwrap_or_default();
+            if !(1..=4).contains(&ease) {
+                return Err(format!("invalid ease: {}", ease));
+            }
+            let Some(card) = self.cards.get_mut(&id) else {
+                found.push(false);
+                continue;
+            };
+            card.reps += 1;
+            if ease == 1 {
+                if card.is_review {
+                    card.lapses += 1;
+                }
+                card.interval = 1;
+            } else {
+                card.interval = card.interval.max(1) * (ease - 1);
+            }
+            card.is_review = true;
+            card.due = card.interval;
+            if card.factor == 0 {
+                card.factor = 2500;
+            }
+            found.push(true);
+        }
+        Ok(json!(found))
+    }
+
+    /// Stores a media file, like Anki a file with the same name but another content is renamed
+    /// unless `deleteExisting` is set.
+    fn store_media_file(&mut self, params: &Value) -> ActionResult {
+        let filename = str_param(params, "filename")?;
+        let data = if let Some(data) = params.get("data").and_then(Value::as_str) {
+            data.to_string()
+        } else if let Some(path) = params.get("path").and_then(Value::as_str) {
+            STANDARD.encode(std::fs::read(path).map_err(|err| err.to_string())?)
+        } else {
+            return Err("You must provide a \"data\", \"path\", or \"url\" field.".to_string());
+        };
+        let delete_existing = params
+            .get("deleteExisting")
+            .and_then(Value::as_bool)
+            .unwrap_or(true);
+        let name = match self.media.get(filename) {
+            Some(existing) if !delete_existing && *existing != data => {
+                let (stem, extension) = filename.rsplit_once('.').unwrap_or((filename, ""));
+                (1..)
+                    .map(|n| format!("{}-{}.{}", stem, n, extension))
+                    .find(|name| {
+                        self.media
+                            .get(name)
+                            .is_none_or(|existing| *existing == data)
+                    })
+                    .unwrap()
+            }
+            _ => filename.to_string(),
+        };
+        self.media.insert(name.clone(), data);
+        Ok(json!(name))
+    }
+
+    fn set_suspended(&mut self, params: &Value, suspended: bool) -> ActionResult {
+        let mut changed = false;
+        for id in ids_param(params, "cards")? {
+            let card = self
+                .cards
+                .get_mut(&id)
+                .ok_or_else(|| format!("card was not found: {}", id))?;
+            changed |= card.suspended != suspended;
+            card.suspended = suspended;
+        }
+        Ok(json!(changed))
+    }
+
+    /// Runs a single action, the API key and version are checked by the caller.
+    fn handle(&mut self, action: &str, params: &Value) -> ActionResult {
+        if self.removed_actions.contains(action) {
+            return Err("unsupported action".to_string());
+        }
+        if self.failing_actions.contains(action) {
+            return Err("the collection is busy".to_string());
+        }
+        match action {
+            "version" => Ok(json!(6)),
+            "apiReflect" => {
+                // Every action is listed when no actions are given
+                let requested = match params.get("actions") {
+                    Some(Value::Null) | None => ACTIONS.iter().map(|a| a.to_string()).collect(),
+                    actions => strings(actions),
+                };
+                let actions: Vec<String> = requested
+                    .into_iter()
+                    .filter(|action| {
+                        ACTIONS.contains(&action.as_str()) && !self.removed_actions.contains(action)
+                    })
+                    .collect();
+                Ok(json!({"scopes": ["actions"], "actions": actions}))
+            }
+            "deckNames" => Ok(json!(self.decks.keys().collect::<Vec<_>>())),
+            "deckNamesAndIds" => Ok(json!(self.decks)),
+            "createDeck" => Ok(json!(self.create_deck(str_param(params, "deck")?))),
+            "deleteDecks" => {
+                if param(params, "cardsToo")?.as_bool() != Some(true) {
+                    return Err("Since Anki 2.1.28 it's not possible to delete decks without deleting cards as well".to_string());
+                }
+                let names = strings(params.get("decks"));
+                let is_deleted = |deck: &str| names.iter().any(|name| is_in_deck(deck, name));
+                let cards: BTreeSet<i64> = self
+                    .cards
+                    .values()
+                    .filter(|card| is_deleted(&card.deck))
+                    .map(|card| card.id)
+                    .collect();
+                self.remove_cards(&cards);
+                self.decks.retain(|deck, _| !is_deleted(deck));
+                Ok(Value::Null)
+            }
+            "changeDeck" => {
+                let deck = str_param(params, "deck")?.to_string();
+                self.create_deck(&deck);
+                for id in ids_param(params, "cards")? {
+                    if let Some(card) = self.cards.get_mut(&id) {
+                        card.deck = deck.clone();
+                    }
+                }
+                Ok(Value::Null)
+            }
+            "getDecks" => {
+                let mut decks: BTreeMap<&str, Vec<i64>> = BTreeMap::new();
+                for id in ids_param(params, "cards")? {
+                    if let Some(card) = self.cards.get(&id) {
+                        decks.entry(&card.deck).or_default().push(id);
+                    }
+                }
+                Ok(json!(decks))
+            }
+            "modelNames" => Ok(json!(self.models.keys().collect::<Vec<_>>())),
+            "findModelsByName" => strings(params.get("modelNames"))
+                .iter()
+                .map(|name| self.model(name).map(|model| self.model_info(model)))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::from),
+            "createModel" => self.create_model(params),
+            "updateModelStyling" => {
+                let model = param(params, "model")?;
+                let name = str_param(model, "name")?;
+                self.model(name)?;
+                self.models.get_mut(name).unwrap().css = str_param(model, "css")?.to_string();
+                Ok(Value::Null)
+            }
+            "updateModelTemplates" => {
+                let model = param(params, "model")?;
+                let name = str_param(model, "name")?;
+                self.model(name)?;
+                let templates = param(model, "templates")?;
+                for (template, front, back) in &mut self.models.get_mut(name).unwrap().templates {
+                    if let Some(sides) = templates.get(template.as_str()) {
+                        if let Some(value) = sides.get("Front").and_then(Value::as_str) {
+                            *front = value.to_string();
+                        }
+                        if let Some(value) = sides.get("Back").and_then(Value::as_str) {
+                            *back = value.to_string();
+                        }
+                    }
+                }
+                Ok(Value::Null)
+            }
+            "modelFieldAdd" => {
+                let model_name = str_param(params, "modelName")?;
+                let field = str_param(params, "fieldName")?.to_string();
+                self.model(model_name)?;
+                let model = self.models.get_mut(model_name).unwrap();
+                let index = params
+                    .get("index")
+                    .and_then(Value::as_u64)
+                    .map_or(model.fields.len(), |index| index as usize)
+                    .min(model.fields.len());
+                model.fields.insert(index, field.clone());
+                for note in self.notes.values_mut() {
+                    if note.model == model_name {
+                        note.fields.insert(index, (field.clone(), String::new()));
+                    }
+                }
+                Ok(Value::Null)
+            }
+            "modelTemplateAdd" => self.model_template_add(params),
+            "modelTemplateRemove" => self.model_template_remove(params),
+            "findNotes" => self
+                .find_notes(str_param(params, "query")?)
+                .map(Value::from),
+            "findCards" => self
+                .find_cards(str_param(params, "query")?)
+                .map(Value::from),
+            "notesInfo" => {
+                let ids = match params.get("query") {
+                    Some(query) => self.find_notes(query.as_str().unwrap_or_default())?,
+                    None => ids_param(params, "notes")?,
+                };
+                Ok(json!(
+                    ids.iter()
+                        .map(|id| self
+                            .notes
+                            .get(id)
+                            .map_or(json!({}), |note| self.note_info(note)))
+                        .collect::<Vec<_>>()
+                ))
+            }
+            "addNote" => self.add_note(param(params, "note")?),
+            "addNotes" => {
+                let notes = param(params, "notes")?
+                    .as_array()
+                    .ok_or("notes is not an array")?;
+                let results: Vec<ActionResult> =
+                    notes.iter().map(|note| self.add_note(note)).collect();
+                if results.iter().any(Result::is_err) {
+                    let errors: Vec<String> = results
+                        .into_iter()
+                        .filter_map(Result::err)
+                        .map(|err| format!("'{}'", err))
+                        .collect();
+                    return Err(format!("[{}]", errors.join(", ")));
+                }
+                Ok(json!(results.into_iter().flatten().collect::<Vec<_>>()))
+            }
+            "updateNoteFields" => self.update_note(param(params, "note")?, true, false),
+            "updateNote" => self.update_note(param(params, "note")?, true, true),
+            "updateNoteModel" => self.update_note_model(param(params, "note")?),
+            "deleteNotes" => {
+                let notes: BTreeSet<i64> = ids_param(params, "notes")?.into_iter().collect();
+                let cards: BTreeSet<i64> = self
+                    .cards
+                    .values()
+                    .filter(|card| notes.contains(&card.note))
+                    .map(|card| card.id)
+                    .collect();
+                self.remove_cards(&cards);
+                self.notes.retain(|id, _| !notes.contains(id));
+                Ok(Value::Null)
+            }
+            "suspend" => self.set_suspended(params, true),
+            "unsuspend" => self.set_suspended(params, false),
+            "areSuspended" => Ok(json!(
+                ids_param(params, "cards")?
+                    .iter()
+                    .map(|id| self.cards.get(id).map(|card| card.suspended))
+                    .collect::<Vec<_>>()
+            )),
+            "cardsInfo" => Ok(json!(
+                ids_param(params, "cards")?
+                    .iter()
+                    .map(|id| self
+                        .cards
+                        .get(id)
+                        .map_or(json!({}), |card| self.card_info(card)))
+                    .collect::<Vec<_>>()
+            )),
+            "cardsToNotes" => {
+                let mut notes = BTreeSet::new();
+                for id in ids_param(params, "cards")? {
+                    if let Some(card) = self.cards.get(&id) {
+                        notes.insert(card.note);
+                    }
+                }
+                Ok(json!(notes))
+            }
+            "setDueDate" => self.set_due_date(params),
+            "forgetCards" => {
+                for id in ids_param(params, "cards")? {
+                    let card = self.card_mut(id)?;
+                    // The review counts are kept, like with `resetCounts: false`
+                    card.is_review = false;
+                    card.due = card.id;
+                    card.interval = 0;
+                    card.factor = 0;
+                }
+                Ok(Value::Null)
+            }
+            "answerCards" => self.answer_cards(params),
+            "storeMediaFile" => self.store_media_file(params),
+            "retrieveMediaFile" => Ok(self
+                .media
+                .get(str_param(params, "filename")?)
+                .map_or(json!(false), |data| json!(data))),
+            "getMediaFilesNames" => {
+                let pattern: Vec<char> = str_param(params, "pattern")?.chars().collect();
+                Ok(json!(
+                    self.media
+                        .keys()
+                        .filter(|name| matches_pattern(&name.chars().collect::<Vec<_>>(), &pattern))
+                        .collect::<Vec<_>>()
+                ))
+            }
+            "getMediaDirPath" => Ok(json!("/fake/collection.media")),
+            "deleteMediaFile" => {
+                self.media.remove(str_param(params, "filename")?);
+                Ok(Value::Null)
+            }
+            "getProfiles" => Ok(json!(self.profiles)),
+            "getActiveProfile" => Ok(json!(self.profile)),
+            "loadProfile" => {
+                let name = str_param(params, "name")?;
+                if !self.profiles.iter().any(|profile| profile == name) {
+                    return Ok(json!(false));
+                }
+                self.profile = name.to_string();
+                Ok(json!(true))
+            }
+            "sync" => {
+                self.num_ankiweb_syncs += 1;
+                Ok(Value::Null)
+            }
+            "guiBrowse" => {
+                let query = str_param(params, "query")?;
+                let cards = self.find_cards(query)?;
+                self.browser_query = Some(query.to_string());
+                Ok(json!(cards))
+            }
+            "guiEditNote" => {
+                let id = param(params, "note")?.as_i64().unwrap_or_default();
+                if !self.notes.contains_key(&id) {
+                    return Err(format!("note was not found: {}", id));
+                }
+                self.edited_note = Some(id);
+                Ok(Value::Null)
+            }
+            _ => Err("unsupported action".to_string()),
+        }
+    }
+}
+
+/// Fake AnkiConnect listening on a random local port until the end of the test process.
+#[derive(Debug, Clone)]
+pub struct FakeAnkiConnect {
+    port: u16,
+    key: Option<String>,
+    collection: Arc<Mutex<Collection>>,
+    actions: Arc<Mutex<Vec<String>>>,
+}
+
+impl FakeAnkiConnect {
+    pub fn start() -> Self {
+        Self::start_with_key(None)
+    }
+
+    /// Starts a fake that rejects requests without the given API key, like AnkiConnect with
+    /// `apiKey` set in its config.
+    pub fn start_with_key(key: Option<&str>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind fake AnkiConnect");
+        let fake = FakeAnkiConnect {
+            port: listener.local_addr().unwrap().port(),
+            key: key.map(str::to_string),
+            collection: Arc::default(),
+            actions: Arc::default(),
+        };
+        let server = fake.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let server = server.clone();
+                thread::spawn(move || server.serve(stream));
+            }
+        });
+        fake
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn collection(&self) -> MutexGuard<'_, Collection> {
+        self.collection.lock().unwrap()
+    }
+
+    /// Names of the actions received so far, including `multi` and the actions it contains.
+    pub fn actions(&self) -> Vec<String> {
+        self.actions.lock().unwrap().clone()
+    }
+
+    pub fn clear_actions(&self) {
+        self.actions.lock().unwrap().clear();
+    }
+
+    /// Answers the HTTP requests of a connection until it is closed.
+    fn serve(&self, stream: TcpStream) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut stream = stream;
+        while let Some(body) = read_request(&mut reader) {
+            let response = match serde_json::from_slice::<Value>(&body) {
+                Ok(request) => self.respond(&request),
+                Err(err) => json!({"result": null, "error": err.to_string()}),
+            };
+            let body = response.to_string();
+            let written = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            if written.is_err() {
+                return;
+            }
+        }
+    }
+
+    fn respond(&self, request: &Value) -> Value {
+        let action = request
+            .get("action")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let params = request.get("params").cloned().unwrap_or(json!({}));
+        self.actions.lock().unwrap().push(action.to_string());
+        let result = if action == "multi" {
+            self.check_key(request).map(|_| {
+                let actions = params
+                    .get("actions")
+                    .and_then(Value::as_array)
+                    .cloned()
+                    .unwrap_or_default();
+                Value::from(
+                    actions
+                        .iter()
+                        .map(|action| self.respond(action))
+                        .collect::<Vec<_>>(),
+                )
+            })
+        } else {
+            self.check_key(request)
+                .and_then(|_| self.collection().handle(action, &params))
+        };
+        match result {
+            Ok(result) => json!({"result": result, "error": null}),
+            Err(err) => json!({"result": null, "error": err}),
+        }
+    }
+
+    fn check_key(&self, request: &Value) -> Result<(), String> {
+        match &self.key {
+            Some(key) if request.get("key").and_then(Value::as_str) != Some(key) => {
+                Err("valid api key must be provided".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Reads the body of the next HTTP request, `None` once the connection is closed.
+fn read_request(reader: &mut BufReader<TcpStream>) -> Option<Vec<u8>> {
+    let mut content_length = None;
+    let mut is_chunked = false;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse::<usize>().ok();
+            } else if name.eq_ignore_ascii_case("transfer-encoding") {
+                is_chunked = value.eq_ignore_ascii_case("chunked");
+            }
+        }
+    }
+
+    let mut body = Vec::new();
+    if is_chunked {
+        loop {
+            line.clear();
+            reader.read_line(&mut line).ok()?;
+            let size = usize::from_str_radix(line.trim(), 16).ok()?;
+            let mut chunk = vec![0; size + 2];
+            reader.read_exact(&mut chunk).ok()?;
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    } else {
+        body.resize(content_length.unwrap_or(0), 0);
+        reader.read_exact(&mut body).ok()?;
+    }
+    Some(body)
+}
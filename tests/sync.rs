@@ -0,0 +1,1048 @@
+//! End to end tests of `md2anki sync` against the demo directory and a fake AnkiConnect.
+mod fake_anki_connect;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use fake_anki_connect::FakeAnkiConnect;
+use markdown_to_anki::anki_connect::AnkiConnectClient;
+use markdown_to_anki::anki_connect::card::{CardId, CardType};
+use markdown_to_anki::anki_connect::cards_client::params::{CardAnswer, Ease};
+use markdown_to_anki::anki_connect::media_client::params::OnCollision;
+
+const DECK: &str = "Demo";
+const NUM_DEMO_NOTES: usize = 53;
+
+/// Copies the demo directory to a new temporary directory.
+fn demo_dir(name: &str) -> PathBuf {
+    fn copy_dir(from: &Path, to: &Path) {
+        fs::create_dir_all(to).unwrap();
+        for entry in fs::read_dir(from).unwrap() {
+            let path = entry.unwrap().path();
+            let target = to.join(path.file_name().unwrap());
+            if path.is_dir() {
+                copy_dir(&path, &target);
+            } else {
+                fs::copy(&path, &target).unwrap();
+            }
+        }
+    }
+
+    let dir = std::env::temp_dir().join(format!("md2anki-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    copy_dir(&Path::new(env!("CARGO_MANIFEST_DIR")).join("demo"), &dir);
+    dir
+}
+
+fn run_md2anki(fake: &FakeAnkiConnect, args: &[&str], input_dir: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_md2anki"))
+        .args(args)
+        .arg(input_dir)
+        .args(["--anki-port", &fake.port().to_string()])
+        .env_remove("ANKI_CONNECT_URL")
+        .env_remove("ANKI_CONNECT_PORT")
+        .env_remove("ANKI_CONNECT_KEY")
+        .output()
+        .expect("run md2anki")
+}
+
+fn md2anki(fake: &FakeAnkiConnect, args: &[&str], input_dir: &Path) -> Output {
+    let output = run_md2anki(fake, args, input_dir);
+    assert!(output.status.success(), "{:?}", output);
+    output
+}
+
+/// Runs a sync that is expected to fail, and returns what it logged.
+fn failing_sync(fake: &FakeAnkiConnect, input_dir: &Path, args: &[&str]) -> String {
+    let output = run_md2anki(fake, &[&["sync", "--deck", DECK], args].concat(), input_dir);
+    assert!(!output.status.success(), "{:?}", output);
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+fn sync(fake: &FakeAnkiConnect, input_dir: &Path, args: &[&str]) -> Output {
+    md2anki(fake, &[&["sync", "--deck", DECK], args].concat(), input_dir)
+}
+
+fn num_notes(fake: &FakeAnkiConnect, deck: &str) -> usize {
+    fake.collection().notes_in_deck(deck).len()
+}
+
+/// Id of the note whose markdown id ends with the given block id.
+fn note_id(fake: &FakeAnkiConnect, block_id: &str) -> i64 {
+    fake.collection()
+        .notes
+        .values()
+        .find(|note| note.fields[0].1.ends_with(block_id))
+        .map(|note| note.id)
+        .expect("note exists")
+}
+
+/// Client talking to the fake directly, without going through md2anki.
+fn client(fake: &FakeAnkiConnect) -> AnkiConnectClient {
+    AnkiConnectClient::new(None, Some(u32::from(fake.port())), None)
+}
+
+fn count_actions(fake: &FakeAnkiConnect, action: &str) -> usize {
+    fake.actions().iter().filter(|name| *name == action).count()
+}
+
+fn model_css(fake: &FakeAnkiConnect) -> String {
+    fake.collection()
+        .models
+        .values()
+        .next()
+        .unwrap()
+        .css
+        .clone()
+}
+
+/// Values of the `Tts` field of the notes of a deck.
+fn tts_fields(fake: &FakeAnkiConnect, deck: &str) -> Vec<String> {
+    fake.collection()
+        .notes_in_deck(deck)
+        .iter()
+        .map(|note| note.field("Tts").unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn sync_adds_the_demo_notes() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("add");
+
+    sync(&fake, &input_dir, &[]);
+
+    assert_eq!(num_notes(&fake, DECK), NUM_DEMO_NOTES);
+    let collection = fake.collection();
+    assert!(collection.models.contains_key("md2anki Basic"));
+    for deck in ["Demo::arules", "Demo::nouns::words", "Demo::other"] {
+        assert!(collection.decks.contains_key(deck), "missing deck {}", deck);
+    }
+    assert!(input_dir.join(".md2anki").join("state.json").is_file());
+}
+
+#[test]
+fn dry_run_does_not_change_anki() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("dry-run");
+
+    let output = sync(&fake, &input_dir, &["--dry-run"]);
+
+    let plan = String::from_utf8_lossy(&output.stdout);
+    assert!(plan.contains("Demo::nouns::words"), "{}", plan);
+    let collection = fake.collection();
+    assert!(collection.notes.is_empty());
+    assert!(collection.models.is_empty());
+}
+
+#[test]
+fn second_sync_skips_unchanged_notes() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("unchanged");
+    sync(&fake, &input_dir, &[]);
+    fake.clear_actions();
+
+    sync(&fake, &input_dir, &[]);
+
+    assert_eq!(num_notes(&fake, DECK), NUM_DEMO_NOTES);
+    for action in ["addNote", "updateNote", "changeDeck", "deleteNotes"] {
+        assert_eq!(count_actions(&fake, action), 0, "unexpected {}", action);
+    }
+}
+
+#[test]
+fn edited_callout_updates_its_note() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("update");
+    sync(&fake, &input_dir, &[]);
+    fake.clear_actions();
+
+    let path = input_dir.join("other.md");
+    let content = fs::read_to_string(&path).unwrap();
+    fs::write(&path, content.replacen("> foo", "> bar", 1)).unwrap();
+    sync(&fake, &input_dir, &[]);
+
+    assert_eq!(count_actions(&fake, "updateNote"), 1);
+    assert_eq!(count_actions(&fake, "addNote"), 0);
+    let note_id = note_id(&fake, "tcvgsamwfz");
+    let collection = fake.collection();
+    let note = &collection.notes[&note_id];
+    assert!(note.fields.iter().any(|(_, value)| value.contains("bar")));
+}
+
+#[test]
+fn moved_file_moves_its_notes() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("move");
+    sync(&fake, &input_dir, &[]);
+
+    fs::rename(
+        input_dir.join("other.md"),
+        input_dir.join("nouns").join("other.md"),
+    )
+    .unwrap();
+    sync(&fake, &input_dir, &[]);
+
+    assert_eq!(num_notes(&fake, DECK), NUM_DEMO_NOTES);
+    assert_eq!(num_notes(&fake, "Demo::other"), 0);
+    assert_eq!(num_notes(&fake, "Demo::nouns::other"), 3);
+}
+
+#[test]
+fn updated_and_moved_notes_are_sent_together() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("update_and_move");
+    sync(&fake, &input_dir, &[]);
+    fake.clear_actions();
+
+    let path = input_dir.join("other.md");
+    let content = fs::read_to_string(&path).unwrap();
+    fs::write(&path, content.replacen("> foo", "> bar", 1)).unwrap();
+    fs::rename(&path, input_dir.join("nouns").join("other.md")).unwrap();
+    sync(&fake, &input_dir, &[]);
+
+    assert_eq!(count_actions(&fake, "updateNote"), 1);
+    assert_eq!(count_actions(&fake, "changeDeck"), 3);
+    assert_eq!(count_actions(&fake, "multi"), 1);
+    assert_eq!(num_notes(&fake, "Demo::nouns::other"), 3);
+}
+
+#[test]
+fn missing_action_aborts_the_sync() {
+    let fake = FakeAnkiConnect::start();
+    fake.collection()
+        .removed_actions
+        .insert("updateModelTemplates".to_string());
+    let input_dir = demo_dir("missing_action");
+
+    let stderr = failing_sync(&fake, &input_dir, &[]);
+
+    assert!(
+        stderr.contains("doesn't support `updateModelTemplates`"),
+        "{}",
+        stderr
+    );
+    assert_eq!(count_actions(&fake, "createModel"), 0);
+    assert_eq!(count_actions(&fake, "addNote"), 0);
+}
+
+#[test]
+fn removing_templates_requires_a_flag() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("remove_templates");
+    sync(&fake, &input_dir, &[]);
+    let num_templates =
+        |fake: &FakeAnkiConnect| fake.collection().models["md2anki Basic"].templates.len();
+    let num_cards = fake.collection().cards.len();
+    assert_eq!(num_templates(&fake), 3);
+
+    let stderr = failing_sync(&fake, &input_dir, &["--directions", "recognition,recall"]);
+    assert!(stderr.contains("pass --remove-templates"), "{}", stderr);
+    assert_eq!(num_templates(&fake), 3);
+    assert_eq!(fake.collection().cards.len(), num_cards);
+
+    sync(
+        &fake,
+        &input_dir,
+        &["--directions", "recognition,recall", "--remove-templates"],
+    );
+    assert_eq!(num_templates(&fake), 2);
+    assert_eq!(fake.collection().cards.len(), num_cards - NUM_DEMO_NOTES);
+}
+
+#[test]
+fn recorded_sync_can_be_replayed_offline() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("record");
+    let trace = input_dir.join("trace.jsonl");
+    sync(&fake, &input_dir, &["--record", trace.to_str().unwrap()]);
+    let num_actions = fake.actions().len();
+
+    let replay_dir = demo_dir("replay");
+    let output = sync(&fake, &replay_dir, &["--replay", trace.to_str().unwrap()]);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("ERROR"), "{}", stderr);
+    assert_eq!(fake.actions().len(), num_actions, "replay contacted Anki");
+    assert!(replay_dir.join(".md2anki").join("state.json").exists());
+}
+
+#[test]
+fn sync_of_an_existing_deck_can_be_replayed() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("record_existing");
+    let trace = input_dir.join("trace.jsonl");
+    sync(&fake, &input_dir, &[]);
+    sync(
+        &fake,
+        &input_dir,
+        &["--full", "--record", trace.to_str().unwrap()],
+    );
+
+    for _ in 0..3 {
+        let output = sync(
+            &fake,
+            &input_dir,
+            &["--full", "--replay", trace.to_str().unwrap()],
+        );
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            !stderr.contains("differ from the replayed trace"),
+            "{}",
+            stderr
+        );
+    }
+}
+
+#[test]
+fn replay_of_changed_requests_fails() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("record_changed");
+    let trace = input_dir.join("trace.jsonl");
+    sync(&fake, &input_dir, &["--record", trace.to_str().unwrap()]);
+
+    let replay_dir = demo_dir("replay_changed");
+    let path = replay_dir.join("other.md");
+    let content = fs::read_to_string(&path).unwrap();
+    fs::write(&path, content.replacen("> foo", "> bar", 1)).unwrap();
+    failing_sync(&fake, &replay_dir, &["--replay", trace.to_str().unwrap()]);
+    let report = fs::read_to_string(replay_dir.join("failed_notes.json")).unwrap();
+    assert!(
+        report.contains("differ from the replayed trace"),
+        "{}",
+        report
+    );
+
+    sync(
+        &fake,
+        &replay_dir,
+        &["--replay", trace.to_str().unwrap(), "--replay-loose"],
+    );
+}
+
+#[test]
+fn api_key_is_left_out_of_the_trace() {
+    let fake = FakeAnkiConnect::start_with_key(Some("k3y-0f-th3-us3r"));
+    let input_dir = demo_dir("record_key");
+    let trace = input_dir.join("trace.jsonl");
+
+    sync(
+        &fake,
+        &input_dir,
+        &[
+            "--anki-key",
+            "k3y-0f-th3-us3r",
+            "--record",
+            trace.to_str().unwrap(),
+        ],
+    );
+
+    assert!(count_actions(&fake, "multi") > 0);
+    let trace = fs::read_to_string(&trace).unwrap();
+    assert!(trace.contains("\"action\":\"multi\""), "{}", trace);
+    assert!(!trace.contains("k3y-0f-th3-us3r"), "{}", trace);
+}
+
+#[test]
+fn open_shows_the_notes_of_a_file_in_the_browser() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("open_file");
+    sync(&fake, &input_dir, &[]);
+
+    md2anki(&fake, &["open"], &input_dir.join("other.md"));
+
+    let query = fake
+        .collection()
+        .browser_query
+        .clone()
+        .expect("browser opened");
+    let note_ids = query.strip_prefix("nid:").expect("note id search");
+    assert_eq!(note_ids.split(',').count(), 3);
+}
+
+#[test]
+fn open_edits_the_note_of_a_callout() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("open_callout");
+    sync(&fake, &input_dir, &[]);
+
+    let target = format!("{}#^tcvgsamwfz", input_dir.join("other.md").display());
+    md2anki(&fake, &["open", "--edit"], Path::new(&target));
+
+    let edited_note = fake.collection().edited_note;
+    assert_eq!(edited_note, Some(note_id(&fake, "tcvgsamwfz")));
+}
+
+#[test]
+fn anki_profile_is_loaded_before_syncing() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("anki_profile");
+
+    sync(&fake, &input_dir, &["--anki-profile", "User 2"]);
+
+    assert_eq!(fake.collection().profile, "User 2");
+    assert_eq!(num_notes(&fake, DECK), NUM_DEMO_NOTES);
+}
+
+#[test]
+fn dry_run_does_not_switch_anki_profiles() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("anki_profile_dry_run");
+
+    let stderr = failing_sync(
+        &fake,
+        &input_dir,
+        &["--anki-profile", "User 2", "--dry-run"],
+    );
+    assert!(stderr.contains("'User 2' to be open"), "{}", stderr);
+    assert_eq!(count_actions(&fake, "loadProfile"), 0);
+
+    sync(
+        &fake,
+        &input_dir,
+        &["--anki-profile", "User 1", "--dry-run"],
+    );
+    assert_eq!(fake.collection().profile, "User 1");
+}
+
+#[test]
+fn unknown_anki_profile_aborts_the_sync() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("unknown_anki_profile");
+
+    let stderr = failing_sync(&fake, &input_dir, &["--anki-profile", "Nobody"]);
+
+    assert!(stderr.contains("'Nobody' does not exist"), "{}", stderr);
+    assert_eq!(fake.collection().profile, "User 1");
+    assert_eq!(count_actions(&fake, "addNote"), 0);
+}
+
+#[test]
+fn anki_sync_runs_after_a_successful_sync() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("anki_sync");
+
+    sync(&fake, &input_dir, &["--anki-sync", "--dry-run"]);
+    assert_eq!(fake.collection().num_ankiweb_syncs, 0);
+
+    sync(&fake, &input_dir, &["--anki-sync"]);
+    assert_eq!(fake.collection().num_ankiweb_syncs, 1);
+    let actions = fake.actions();
+    assert_eq!(actions.last().map(String::as_str), Some("sync"));
+}
+
+#[test]
+fn anki_sync_is_skipped_when_notes_fail() {
+    let fake = FakeAnkiConnect::start();
+    fake.collection()
+        .rejected_notes
+        .insert("tcvgsamwfz".to_string());
+    let input_dir = demo_dir("anki_sync_failed");
+
+    failing_sync(&fake, &input_dir, &["--anki-sync"]);
+
+    assert_eq!(fake.collection().num_ankiweb_syncs, 0);
+    assert_eq!(count_actions(&fake, "sync"), 0);
+}
+
+#[test]
+fn prune_removes_notes_of_deleted_callouts() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("prune");
+    sync(&fake, &input_dir, &[]);
+
+    fs::remove_file(input_dir.join("other.md")).unwrap();
+    sync(&fake, &input_dir, &[]);
+    assert_eq!(
+        num_notes(&fake, DECK),
+        NUM_DEMO_NOTES,
+        "removed without --prune"
+    );
+
+    sync(&fake, &input_dir, &["--prune"]);
+    assert_eq!(num_notes(&fake, DECK), NUM_DEMO_NOTES - 3);
+    assert!(!fake.collection().decks.contains_key("Demo::other"));
+}
+
+#[test]
+fn failed_pruning_fails_the_sync() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("failed_prune");
+    sync(&fake, &input_dir, &[]);
+    fs::remove_file(input_dir.join("other.md")).unwrap();
+
+    fake.collection()
+        .failing_actions
+        .insert("deleteNotes".to_string());
+    let stderr = failing_sync(&fake, &input_dir, &["--prune"]);
+    assert!(stderr.contains("removing orphaned notes"), "{}", stderr);
+    assert_eq!(num_notes(&fake, DECK), NUM_DEMO_NOTES);
+
+    // The notes are removed, but the empty deck is left
+    fake.collection().failing_actions = ["deleteDecks".to_string()].into();
+    let stderr = failing_sync(&fake, &input_dir, &["--prune"]);
+    assert!(stderr.contains("deleting empty decks"), "{}", stderr);
+    assert_eq!(num_notes(&fake, DECK), NUM_DEMO_NOTES - 3);
+
+    fake.collection().failing_actions.clear();
+    sync(&fake, &input_dir, &["--prune"]);
+    assert!(!fake.collection().decks.contains_key("Demo::other"));
+}
+
+#[test]
+fn deck_name_with_spaces_is_synced() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("deck_with_spaces");
+    let deck = &["--deck", "My Demo"];
+    md2anki(&fake, &[&["sync"], &deck[..]].concat(), &input_dir);
+    assert_eq!(num_notes(&fake, "My Demo"), NUM_DEMO_NOTES);
+
+    fs::remove_file(input_dir.join("other.md")).unwrap();
+    md2anki(
+        &fake,
+        &[&["sync", "--prune"], &deck[..]].concat(),
+        &input_dir,
+    );
+
+    assert_eq!(num_notes(&fake, "My Demo"), NUM_DEMO_NOTES - 3);
+    assert!(!fake.collection().decks.contains_key("My Demo::other"));
+}
+
+#[test]
+fn user_css_is_layered_on_top_of_the_default_css() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("user_css");
+    let css_file = input_dir.join("user.css");
+    fs::write(&css_file, ".card { color: rebeccapurple; }").unwrap();
+    let css_arg = css_file.to_str().unwrap();
+
+    sync(&fake, &input_dir, &["--css", css_arg]);
+    let css = model_css(&fake);
+    let default = css.find("--ctp-rosewater").expect("default stylesheet");
+    assert!(css.contains(r#".callout[data-callout="rule"]"#), "icons");
+    let user = css.find("rebeccapurple").expect("user stylesheet");
+    assert!(default < user, "user rules come last to take precedence");
+
+    sync(&fake, &input_dir, &["--css", css_arg, "--no-default-css"]);
+    let css = model_css(&fake);
+    assert!(css.contains("rebeccapurple"), "{}", css);
+    assert!(!css.contains("--ctp-rosewater"), "{}", css);
+    assert!(!css.contains("data-callout"), "{}", css);
+}
+
+#[test]
+fn notes_of_another_model_are_converted_with_a_flag() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("change_model");
+    sync(&fake, &input_dir, &[]);
+    let note = note_id(&fake, "tcvgsamwfz");
+    let cards: Vec<i64> = fake
+        .collection()
+        .note_cards(note)
+        .iter()
+        .map(|card| card.id)
+        .collect();
+
+    let output = sync(&fake, &input_dir, &["--model-name", "Renamed"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("pass --change-model"), "{}", stderr);
+    assert!(stderr.contains("tcvgsamwfz"), "{}", stderr);
+    assert_eq!(count_actions(&fake, "updateNoteModel"), 0);
+    assert_ne!(fake.collection().notes[&note].model, "Renamed");
+
+    sync(
+        &fake,
+        &input_dir,
+        &["--model-name", "Renamed", "--change-model"],
+    );
+    let collection = fake.collection();
+    assert!(
+        collection
+            .notes_in_deck(DECK)
+            .iter()
+            .all(|note| note.model == "Renamed")
+    );
+    assert_eq!(collection.notes_in_deck(DECK).len(), NUM_DEMO_NOTES);
+    let converted: Vec<i64> = collection
+        .note_cards(note)
+        .iter()
+        .map(|card| card.id)
+        .collect();
+    assert_eq!(converted, cards, "the cards and their history are kept");
+}
+
+#[test]
+fn cards_can_be_inspected_and_rescheduled() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("card_actions");
+    sync(&fake, &input_dir, &[]);
+    let client = client(&fake);
+    let cards_client = client.cards();
+
+    let card_ids = cards_client.find_cards("deck:\"Demo::other\"").unwrap();
+    let cards: Vec<&CardId> = card_ids.iter().collect();
+    assert_eq!(cards.len(), 3 * 3);
+    let notes = cards_client.cards_to_notes(&cards).unwrap();
+    assert_eq!(notes.len(), 3);
+    assert!(notes.contains(&note_id(&fake, "tcvgsamwfz").into()));
+    let info = cards_client.cards_info(&cards).unwrap();
+    assert!(info.iter().all(|card| card.deck_name == "Demo::other"
+        && card.kind == CardType::New
+        && !card.is_suspended()));
+
+    let card = &cards[..1];
+    assert!(cards_client.suspend(card).unwrap());
+    assert_eq!(
+        cards_client.are_suspended(&cards[..2]).unwrap(),
+        [Some(true), Some(false)]
+    );
+    assert!(cards_client.cards_info(card).unwrap()[0].is_suspended());
+    assert!(cards_client.unsuspend(card).unwrap());
+
+    assert!(cards_client.set_due_date(card, "3!").unwrap());
+    let info = &cards_client.cards_info(card).unwrap()[0];
+    assert_eq!(
+        (info.kind, info.interval, info.ease()),
+        (CardType::Review, 3, 2.5)
+    );
+
+    let answers = [
+        CardAnswer::new(*cards[0], Ease::Good),
+        CardAnswer::new(CardId(1), Ease::Good),
+    ];
+    assert_eq!(cards_client.answer_cards(&answers).unwrap(), [true, false]);
+    cards_client
+        .answer_cards(&[CardAnswer::new(*cards[0], Ease::Again)])
+        .unwrap();
+    let info = &cards_client.cards_info(card).unwrap()[0];
+    assert_eq!((info.reps, info.lapses, info.interval), (2, 1, 1));
+
+    assert!(cards_client.forget_cards(card).unwrap());
+    let info = &cards_client.cards_info(card).unwrap()[0];
+    assert_eq!((info.kind, info.interval), (CardType::New, 0));
+    assert_eq!(info.reps, 2, "the review history is kept");
+}
+
+#[cfg(feature = "reqwest_async")]
+#[test]
+fn async_client_reads_and_changes_the_collection() {
+    use markdown_to_anki::anki_connect::async_client::AsyncAnkiConnectClient;
+
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("async_client");
+    sync(&fake, &input_dir, &[]);
+    let client = AsyncAnkiConnectClient::new(None, Some(u32::from(fake.port())), None);
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    runtime.block_on(async {
+        assert!(client.test_connection().await.unwrap());
+        let deck = client.decks().create_deck("Async").await.unwrap();
+        assert_eq!(
+            client.decks().find_deck_id_by_name("Async").await.unwrap(),
+            deck
+        );
+
+        let notes = client
+            .notes()
+            .find_notes_ids_by_deck_name("Demo::other")
+            .await
+            .unwrap();
+        assert_eq!(notes.len(), 3);
+        let info = client.notes().notes_info_by_id(&notes).await.unwrap();
+        let cards: Vec<_> = info.iter().flat_map(|note| &note.cards).collect();
+        assert!(client.decks().change_deck(cards, "Async").await.unwrap());
+        assert_eq!(client.models().get_all_names().await.unwrap().len(), 1);
+    });
+
+    assert_eq!(num_notes(&fake, "Async"), 3);
+    assert_eq!(num_notes(&fake, "Demo::other"), 0);
+}
+
+#[test]
+fn media_files_can_be_stored_and_retrieved() {
+    let fake = FakeAnkiConnect::start();
+    let client = client(&fake);
+    let media = client.media();
+
+    let name = media
+        .store_data("hello.txt", b"hello", OnCollision::Replace)
+        .unwrap();
+    assert_eq!(name, "hello.txt");
+    assert_eq!(media.retrieve_file("hello.txt").unwrap().unwrap(), b"hello");
+
+    // Another content is stored under a new name, unless the file is replaced or kept
+    let renamed = media
+        .store_data("hello.txt", b"bye", OnCollision::Rename)
+        .unwrap();
+    assert_ne!(renamed, "hello.txt");
+    assert_eq!(media.retrieve_file(&renamed).unwrap().unwrap(), b"bye");
+    let same = media
+        .store_data("hello.txt", b"hello", OnCollision::Rename)
+        .unwrap();
+    assert_eq!(same, "hello.txt");
+    let kept = media
+        .store_data("hello.txt", b"bye", OnCollision::Keep)
+        .unwrap();
+    assert_eq!(kept, "hello.txt");
+    assert_eq!(media.retrieve_file("hello.txt").unwrap().unwrap(), b"hello");
+
+    let dir = demo_dir("media");
+    let image = dir.join("image.svg");
+    fs::write(&image, "<svg></svg>").unwrap();
+    media
+        .store_path("image.svg", &image, OnCollision::Replace)
+        .unwrap();
+    assert_eq!(
+        media.retrieve_file("image.svg").unwrap().unwrap(),
+        b"<svg></svg>"
+    );
+
+    let names = media.get_media_files_names("hello*").unwrap();
+    assert_eq!(names.len(), 2);
+    assert!(names.contains(&renamed), "{:?}", names);
+    assert!(media.exists("image.svg").unwrap());
+    assert!(!media.exists("image*").unwrap());
+
+    assert!(media.delete_file("hello.txt").unwrap());
+    assert_eq!(media.retrieve_file("hello.txt").unwrap(), None);
+    assert!(!media.exists("hello.txt").unwrap());
+}
+
+#[test]
+fn front_matter_overrides_the_tts_of_the_model() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("tts_front_matter");
+    let other = input_dir.join("other.md");
+    let content = fs::read_to_string(&other).unwrap();
+    fs::write(
+        &other,
+        content.replacen("---\n", "---\nmd2anki-tts-lang: zh_CN\n", 1),
+    )
+    .unwrap();
+
+    sync(&fake, &input_dir, &["--tts-lang", "ko_KR"]);
+
+    let front = fake.collection().models.values().next().unwrap().templates[0]
+        .1
+        .clone();
+    assert!(front.contains("{{#Tts}}"), "{}", front);
+    assert!(front.contains("{{tts ko_KR:Front}}"), "{}", front);
+    let other_tts = tts_fields(&fake, "Demo::other");
+    assert_eq!(other_tts.len(), 3);
+    assert!(
+        other_tts
+            .iter()
+            .all(|tts| tts.starts_with("[anki:tts lang=zh_CN]")),
+        "{:?}",
+        other_tts
+    );
+    assert!(
+        tts_fields(&fake, "Demo::rules")
+            .iter()
+            .all(String::is_empty)
+    );
+
+    // Without the override the notes are read out with the TTS of the model again
+    fs::write(&other, content).unwrap();
+    sync(&fake, &input_dir, &["--tts-lang", "ko_KR"]);
+    assert!(
+        tts_fields(&fake, "Demo::other")
+            .iter()
+            .all(String::is_empty)
+    );
+}
+
+#[test]
+fn wildcards_in_deck_names_are_matched_literally() {
+    let fake = FakeAnkiConnect::start();
+    md2anki(
+        &fake,
+        &["sync", "--deck", "MyXDeck"],
+        &demo_dir("wildcard_other_deck"),
+    );
+    let input_dir = demo_dir("wildcard_deck");
+    md2anki(&fake, &["sync", "--deck", "My_Deck"], &input_dir);
+    assert_eq!(num_notes(&fake, "MyXDeck"), NUM_DEMO_NOTES);
+    assert_eq!(num_notes(&fake, "My_Deck"), NUM_DEMO_NOTES);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_md2anki"))
+        .args(["open", "--deck", "My_Deck"])
+        .args(["--anki-port", &fake.port().to_string()])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let query = fake.collection().browser_query.clone().unwrap();
+    assert_eq!(query, r#"deck:"My\_Deck""#);
+}
+
+#[test]
+fn pruned_notes_can_be_moved_to_the_trash_deck() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("prune_trash");
+    sync(&fake, &input_dir, &[]);
+
+    fs::remove_file(input_dir.join("other.md")).unwrap();
+    sync(&fake, &input_dir, &["--prune", "--orphans", "trash"]);
+
+    assert_eq!(num_notes(&fake, DECK), NUM_DEMO_NOTES - 3);
+    assert_eq!(num_notes(&fake, "Demo Trash"), 3);
+    assert_eq!(count_actions(&fake, "deleteNotes"), 0);
+}
+
+#[test]
+fn pruned_notes_can_be_suspended() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("prune_suspend");
+    sync(&fake, &input_dir, &[]);
+    let note_id = note_id(&fake, "tcvgsamwfz");
+
+    fs::remove_file(input_dir.join("other.md")).unwrap();
+    sync(&fake, &input_dir, &["--prune", "--orphans", "suspend"]);
+
+    assert_eq!(num_notes(&fake, DECK), NUM_DEMO_NOTES);
+    let collection = fake.collection();
+    let cards: Vec<_> = collection
+        .cards
+        .values()
+        .filter(|card| card.note == note_id)
+        .collect();
+    assert!(!cards.is_empty());
+    assert!(cards.iter().all(|card| card.suspended));
+    assert_eq!(count_actions(&fake, "deleteNotes"), 0);
+}
+
+#[test]
+fn pruning_too_many_notes_aborts_the_sync() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("prune_guard");
+    sync(&fake, &input_dir, &[]);
+
+    fs::remove_file(input_dir.join("nouns").join("words.md")).unwrap();
+    let stderr = failing_sync(&fake, &input_dir, &["--prune"]);
+    assert!(
+        stderr.contains("refusing to remove 49 of the 53 notes"),
+        "{}",
+        stderr
+    );
+    assert_eq!(num_notes(&fake, DECK), NUM_DEMO_NOTES);
+
+    sync(
+        &fake,
+        &input_dir,
+        &["--prune", "--max-delete-fraction", "1"],
+    );
+    assert_eq!(num_notes(&fake, DECK), NUM_DEMO_NOTES - 49);
+}
+
+#[test]
+fn delete_recreates_the_deck() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("delete");
+    sync(&fake, &input_dir, &[]);
+    let note_id = note_id(&fake, "tcvgsamwfz");
+
+    sync(&fake, &input_dir, &["--delete"]);
+
+    assert_eq!(num_notes(&fake, DECK), NUM_DEMO_NOTES);
+    assert!(!fake.collection().notes.contains_key(&note_id));
+}
+
+#[test]
+fn conflicting_edit_in_anki_is_kept() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("conflict");
+    sync(&fake, &input_dir, &[]);
+
+    let note_id = note_id(&fake, "tcvgsamwfz");
+    let field = fake.collection().notes[&note_id].fields[1].0.clone();
+    fake.collection()
+        .edit_note(note_id, &field, "edited in Anki");
+    let path = input_dir.join("other.md");
+    let content = fs::read_to_string(&path).unwrap();
+    fs::write(&path, content.replacen("> foo", "> bar", 1)).unwrap();
+    sync(&fake, &input_dir, &[]);
+
+    assert_eq!(
+        fake.collection().notes[&note_id].field(&field),
+        Some("edited in Anki")
+    );
+}
+
+#[test]
+fn conflict_kept_in_anki_is_only_reported_once() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("conflict_anki");
+    sync(&fake, &input_dir, &[]);
+
+    let note_id = note_id(&fake, "tcvgsamwfz");
+    let field = fake.collection().notes[&note_id].fields[1].0.clone();
+    fake.collection()
+        .edit_note(note_id, &field, "edited in Anki");
+    let path = input_dir.join("other.md");
+    let content = fs::read_to_string(&path).unwrap();
+    fs::write(&path, content.replacen("> foo", "> bar", 1)).unwrap();
+    let output = sync(&fake, &input_dir, &["--on-conflict", "anki"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("kept the content edited in Anki"),
+        "{}",
+        stdout
+    );
+
+    let output = sync(&fake, &input_dir, &["--on-conflict", "anki"]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("Conflicts, edited in Anki"), "{}", stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Skipping 5 unchanged markdown files"),
+        "{}",
+        stderr
+    );
+    assert_eq!(
+        fake.collection().notes[&note_id].field(&field),
+        Some("edited in Anki")
+    );
+}
+
+#[test]
+fn pull_writes_edits_made_in_anki_to_the_callout() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("pull");
+    sync(&fake, &input_dir, &[]);
+
+    let note_id = note_id(&fake, "tcvgsamwfz");
+    let back = fake.collection().notes[&note_id]
+        .field("Back")
+        .unwrap()
+        .replacen("<p>foo</p>", "<p>bar <b>baz</b></p>", 1);
+    fake.collection().edit_note(note_id, "Back", &back);
+    md2anki(&fake, &["pull", "--deck", DECK], &input_dir);
+
+    let content = fs::read_to_string(input_dir.join("other.md")).unwrap();
+    assert!(
+        content.contains("> [!rule] VS + 고 있다\n> bar **baz**\n"),
+        "{}",
+        content
+    );
+    assert!(
+        content.contains("> > [!info]\n> > -ing in English."),
+        "{}",
+        content
+    );
+
+    let output = sync(&fake, &input_dir, &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("Conflicts, edited in Anki"), "{}", stdout);
+    let back = fake.collection().notes[&note_id]
+        .field("Back")
+        .unwrap()
+        .to_string();
+    assert!(back.contains("bar <strong>baz</strong>"), "{}", back);
+}
+
+#[test]
+fn undo_reverts_the_last_sync() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("undo");
+    sync(&fake, &input_dir, &[]);
+
+    md2anki(&fake, &["undo"], &input_dir);
+
+    assert_eq!(num_notes(&fake, DECK), 0);
+}
+
+#[test]
+fn undo_restores_the_notes_of_a_deleted_deck() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("undo_delete");
+    sync(&fake, &input_dir, &[]);
+    sync(&fake, &input_dir, &["--delete"]);
+
+    md2anki(&fake, &["undo"], &input_dir);
+
+    assert_eq!(num_notes(&fake, DECK), NUM_DEMO_NOTES);
+}
+
+#[test]
+fn failed_undo_is_reported() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("failed_undo");
+    sync(&fake, &input_dir, &[]);
+    sync(&fake, &input_dir, &["--delete"]);
+    fake.collection()
+        .rejected_notes
+        .insert("tcvgsamwfz".to_string());
+
+    let output = run_md2anki(&fake, &["undo"], &input_dir);
+    assert!(!output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("could not be reverted"), "{}", stderr);
+}
+
+#[test]
+fn api_key_is_sent_with_every_action() {
+    let fake = FakeAnkiConnect::start_with_key(Some("secret"));
+    let input_dir = demo_dir("key");
+
+    failing_sync(&fake, &input_dir, &[]);
+    assert_eq!(num_notes(&fake, DECK), 0);
+
+    sync(&fake, &input_dir, &["--anki-key", "secret"]);
+    assert_eq!(num_notes(&fake, DECK), NUM_DEMO_NOTES);
+}
+
+#[test]
+fn failed_notes_are_reported() {
+    let fake = FakeAnkiConnect::start();
+    fake.collection()
+        .rejected_notes
+        .insert("tcvgsamwfz".to_string());
+    let input_dir = demo_dir("failed_notes");
+
+    let output = failing_sync(&fake, &input_dir, &[]);
+
+    assert!(output.contains("1 notes failed to sync"), "{}", output);
+    assert_eq!(num_notes(&fake, DECK), NUM_DEMO_NOTES - 1);
+    let report = fs::read_to_string(input_dir.join("failed_notes.json")).unwrap();
+    assert!(report.contains("other.md"), "{}", report);
+    assert!(report.contains("tcvgsamwfz"), "{}", report);
+    assert!(
+        report.contains("cannot create note: rejected"),
+        "{}",
+        report
+    );
+
+    fake.collection().rejected_notes.clear();
+    sync(&fake, &input_dir, &[]);
+    assert_eq!(num_notes(&fake, DECK), NUM_DEMO_NOTES);
+    assert!(!input_dir.join("failed_notes.json").exists());
+}
+
+#[test]
+fn failed_profile_fails_the_sync() {
+    let fake = FakeAnkiConnect::start();
+    let input_dir = demo_dir("profiles");
+    fs::write(
+        input_dir.join("md2anki.toml"),
+        "[profiles.demo]\ninput = \".\"\ndeck = \"Demo\"\n\n\
+         [profiles.unknown-model]\ninput = \".\"\ndeck = \"Other\"\nmodel = \"Unknown\"\n",
+    )
+    .unwrap();
+
+    let output = run_md2anki(&fake, &["sync", "--all-profiles"], &input_dir);
+
+    assert!(!output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("failed to sync the profiles: unknown-model"),
+        "{}",
+        stderr
+    );
+    assert_eq!(num_notes(&fake, DECK), NUM_DEMO_NOTES);
+    assert_eq!(num_notes(&fake, "Other"), 0);
+}